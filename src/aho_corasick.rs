@@ -0,0 +1,494 @@
+//! Implementation of the Aho-Corasick algorithm, which searches for many needles at once in a
+//! single pass over the haystack. Each match reports the index of the pattern that matched, as
+//! well as its offset.
+//!
+//! Overlapping searches report every occurence of every pattern. Non-overlapping searches report
+//! the leftmost match each time, and when more than one pattern matches at the leftmost position,
+//! the `MatchKind` decides between them.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{AhoCorasick, SearchIn};
+//! let needle = AhoCorasick::new(&[&b"he"[..], b"she", b"his", b"hers"]);
+//! let haystack = b"ushers";
+//! assert_eq!(vec![(1, 1), (2, 0), (2, 3)], needle.find_overlapping_patterns_in(haystack).collect::<Vec<_>>());
+//! assert_eq!(Some(1), needle.find_first_in(haystack));
+//! ```
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::marker::PhantomData;
+use matches::Match;
+use super::SearchIn;
+
+const ROOT: usize = 0;
+
+/// How to choose between patterns that match at the same leftmost position, in a non-overlapping search.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Prefer the pattern which was given first when the searcher was constructed.
+    LeftmostFirst,
+    /// Prefer the longest pattern.
+    LeftmostLongest,
+}
+
+struct State {
+    // sorted by symbol
+    transitions: Vec<(usize, usize)>,
+    fail: usize,
+    depth: usize,
+    // patterns which end at this state
+    outputs: Vec<usize>,
+    // the nearest state along the chain of fail links which has any outputs
+    dict: Option<usize>,
+}
+
+impl State {
+    fn new(depth: usize) -> State {
+        State { transitions: Vec::new(), fail: ROOT, depth, outputs: Vec::new(), dict: None }
+    }
+
+    fn child(&self, c: usize) -> Option<usize> {
+        self.transitions.binary_search_by_key(&c, |&(symbol, _)| symbol)
+            .ok()
+            .map(|i| self.transitions[i].1)
+    }
+}
+
+pub struct AhoCorasick <T> {
+    states: Vec<State>,
+    pattern_lens: Vec<usize>,
+    match_kind: MatchKind,
+    _alphabet: PhantomData<T>,
+}
+
+impl <T> AhoCorasick<T>
+    where T: Copy + Into<usize>
+{
+    /// Construct a new Aho-Corasick search object for a set of patterns, which resolves matches at
+    /// the same position by preferring the pattern that comes first.
+    pub fn new<P: AsRef<[T]>>(patterns: &[P]) -> AhoCorasick<T> {
+        AhoCorasick::with_match_kind(patterns, MatchKind::LeftmostFirst)
+    }
+
+    /// Construct a new Aho-Corasick search object for a set of patterns, with a `MatchKind` to
+    /// choose between patterns that match at the same position in non-overlapping searches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{AhoCorasick, MatchKind};
+    /// let patterns = [&b"sam"[..], b"samwise"];
+    /// let first = AhoCorasick::with_match_kind(&patterns, MatchKind::LeftmostFirst);
+    /// let longest = AhoCorasick::with_match_kind(&patterns, MatchKind::LeftmostLongest);
+    /// assert_eq!(vec![(0, 0)], first.find_patterns_in(b"samwise").collect::<Vec<_>>());
+    /// assert_eq!(vec![(0, 1)], longest.find_patterns_in(b"samwise").collect::<Vec<_>>());
+    /// ```
+    pub fn with_match_kind<P: AsRef<[T]>>(patterns: &[P], match_kind: MatchKind) -> AhoCorasick<T> {
+        let mut states = vec![State::new(0)];
+        let mut pattern_lens = Vec::with_capacity(patterns.len());
+        for (index, pattern) in patterns.iter().enumerate() {
+            let pattern = pattern.as_ref();
+            let mut state = ROOT;
+            for &c in pattern {
+                let c: usize = c.into();
+                state = match states[state].transitions.binary_search_by_key(&c, |&(symbol, _)| symbol) {
+                    Ok(i) => states[state].transitions[i].1,
+                    Err(i) => {
+                        let child = states.len();
+                        let depth = states[state].depth + 1;
+                        states.push(State::new(depth));
+                        states[state].transitions.insert(i, (c, child));
+                        child
+                    }
+                };
+            }
+            states[state].outputs.push(index);
+            pattern_lens.push(pattern.len());
+        }
+        build_fail_links(&mut states);
+        AhoCorasick { states, pattern_lens, match_kind, _alphabet: PhantomData }
+    }
+
+    /// The number of patterns that this object searches for.
+    pub fn pattern_count(&self) -> usize {
+        self.pattern_lens.len()
+    }
+
+    /// The length of the pattern with the given index.
+    pub fn pattern_len(&self, pattern: usize) -> usize {
+        self.pattern_lens[pattern]
+    }
+
+    /// The `MatchKind` used for non-overlapping searches.
+    pub fn match_kind(&self) -> MatchKind {
+        self.match_kind
+    }
+
+    /// Returns an iterator that will produce the index of each match in the haystack, together with
+    /// the index of the pattern that matched, as `(offset, pattern)`. This iterator will not find
+    /// overlapping matches.
+    pub fn find_patterns_in<'a>(&'a self, haystack: &'a [T]) -> AhoCorasickMatches<'a, T> {
+        AhoCorasickMatches::new(self, haystack, false)
+    }
+
+    /// Returns an iterator that will produce the index of each match in the haystack, together with
+    /// the index of the pattern that matched, as `(offset, pattern)`. This iterator will find all
+    /// overlapping matches of all patterns, ordered by the position where they end.
+    pub fn find_overlapping_patterns_in<'a>(&'a self, haystack: &'a [T]) -> AhoCorasickMatches<'a, T> {
+        AhoCorasickMatches::new(self, haystack, true)
+    }
+
+    /// Returns an iterator over the matches in the haystack, as `Match`es which span the pattern
+    /// that matched and hold its index. This iterator will not find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::AhoCorasick;
+    /// let keywords = AhoCorasick::new(&["fn", "struct"]);
+    /// let haystack = b"pub struct S; fn f() {}";
+    /// let found: Vec<_> = keywords.find_matches_in(haystack).map(|m| (m.slice(haystack), m.pattern())).collect();
+    /// assert_eq!(vec![(&b"struct"[..], 1), (&b"fn"[..], 0)], found);
+    /// ```
+    pub fn find_matches_in<'a>(&'a self, haystack: &'a [T]) -> AhoCorasickMatchIter<'a, T> {
+        AhoCorasickMatchIter { matches: self.find_patterns_in(haystack) }
+    }
+
+    /// Returns an iterator over the matches in the haystack, as `Match`es which span the pattern
+    /// that matched and hold its index. This iterator will find all overlapping matches of all
+    /// patterns, ordered by the position where they end.
+    pub fn find_overlapping_matches_in<'a>(&'a self, haystack: &'a [T]) -> AhoCorasickMatchIter<'a, T> {
+        AhoCorasickMatchIter { matches: self.find_overlapping_patterns_in(haystack) }
+    }
+
+    #[inline]
+    fn next_state(&self, mut state: usize, c: usize) -> usize {
+        loop {
+            if let Some(next) = self.states[state].child(c) {
+                return next;
+            } else if state == ROOT {
+                return ROOT;
+            }
+            state = self.states[state].fail;
+        }
+    }
+
+    // Calls `f` with each pattern that ends at `state`, longest first
+    #[inline]
+    fn for_each_output<F: FnMut(usize)>(&self, state: usize, mut f: F) {
+        let mut state = Some(state);
+        while let Some(s) = state {
+            for &pattern in &self.states[s].outputs {
+                f(pattern);
+            }
+            state = self.states[s].dict;
+        }
+    }
+
+    // Orders matches that start at the same position, so that the one preferred by the `MatchKind`
+    // comes first
+    #[inline]
+    fn rank(&self, end: usize, pattern: usize) -> usize {
+        match self.match_kind {
+            MatchKind::LeftmostFirst => pattern,
+            MatchKind::LeftmostLongest => usize::MAX - end,
+        }
+    }
+}
+
+// Compute the fail link of every state, in breadth-first order, so that the fail links of all
+// shallower states are already known.
+fn build_fail_links(states: &mut [State]) {
+    // the root's children fall back to the root
+    let mut queue: VecDeque<usize> = states[ROOT].transitions.iter().map(|&(_, child)| child).collect();
+    if !states[ROOT].outputs.is_empty() {
+        for &child in &queue {
+            states[child].dict = Some(ROOT);
+        }
+    }
+    while let Some(state) = queue.pop_front() {
+        for i in 0 .. states[state].transitions.len() {
+            let (c, child) = states[state].transitions[i];
+            let mut fail = states[state].fail;
+            let child_fail = loop {
+                if let Some(next) = states[fail].child(c) {
+                    break next;
+                }
+                if fail == ROOT {
+                    break ROOT;
+                }
+                fail = states[fail].fail;
+            };
+            states[child].fail = child_fail;
+            states[child].dict = if states[child_fail].outputs.is_empty() {
+                states[child_fail].dict
+            } else {
+                Some(child_fail)
+            };
+            queue.push_back(child);
+        }
+    }
+}
+
+
+impl <T> SearchIn<[T]> for AhoCorasick<T>
+    where T: Copy + Into<usize>
+{
+    type Iter<'a> = AhoCorasickIter<'a, T> where Self: 'a;
+
+    /// Returns an iterator that will produce the indices of matches of any of the patterns in the
+    /// haystack. This iterator will not find overlapping matches.
+    fn find_in<'a>(&'a self, haystack: &'a [T]) -> AhoCorasickIter<'a, T> {
+        AhoCorasickIter { matches: self.find_patterns_in(haystack) }
+    }
+
+    /// Returns an iterator that will produce the indices of all matches of all of the patterns in
+    /// the haystack, including overlapping matches.
+    fn find_overlapping_in<'a>(&'a self, haystack: &'a [T]) -> AhoCorasickIter<'a, T> {
+        AhoCorasickIter { matches: self.find_overlapping_patterns_in(haystack) }
+    }
+}
+
+
+/// An iterator over matches, which produces `(offset, pattern)` pairs.
+pub struct AhoCorasickMatches <'a, T: 'a> {
+    searcher: &'a AhoCorasick<T>,
+    haystack: &'a [T],
+    position: usize,
+    overlapping_matches: bool,
+    state: usize,
+    // matches that have been found by an overlapping search, but not yet produced
+    pending: VecDeque<(usize, usize)>,
+    // matches that have been found by a non-overlapping search, as `(start, rank, pattern, end)`,
+    // so that the smallest is the leftmost match which is preferred by the `MatchKind`
+    candidates: BinaryHeap<Reverse<(usize, usize, usize, usize)>>,
+    // the offset where the next non-overlapping match may start
+    next_start: usize,
+}
+
+impl <'a, T> AhoCorasickMatches<'a, T>
+    where T: Copy + Into<usize>
+{
+    fn new(searcher: &'a AhoCorasick<T>, haystack: &'a [T], overlapping_matches: bool) -> AhoCorasickMatches<'a, T> {
+        let mut pending = VecDeque::new();
+        let mut candidates = BinaryHeap::new();
+        for &pattern in &searcher.states[ROOT].outputs {
+            if overlapping_matches {
+                pending.push_back((0, pattern));
+            } else {
+                candidates.push(Reverse((0, searcher.rank(0, pattern), pattern, 0)));
+            }
+        }
+        AhoCorasickMatches { searcher, haystack, position: 0, overlapping_matches, state: ROOT, pending, candidates, next_start: 0 }
+    }
+
+    // The automaton carries on from where it was after the previous match, rather than going back
+    // to its start, so each symbol of the haystack is only visited once.
+    fn next_leftmost(&mut self) -> Option<(usize, usize)> {
+        let searcher = self.searcher;
+        loop {
+            if let Some(&Reverse((start, _, pattern, end))) = self.candidates.peek() {
+                // no match that is still in progress can start early enough to be preferred
+                if self.position == self.haystack.len() || self.position - searcher.states[self.state].depth > start {
+                    let next_start = if end > start { end } else { end + 1 };
+                    while self.candidates.peek().is_some_and(|&Reverse((start, _, _, _))| start < next_start) {
+                        self.candidates.pop();
+                    }
+                    self.next_start = next_start;
+                    return Some((start, pattern));
+                }
+            } else if self.position == self.haystack.len() {
+                return None;
+            }
+            self.state = searcher.next_state(self.state, self.haystack[self.position].into());
+            self.position += 1;
+            let end = self.position;
+            let next_start = self.next_start;
+            let candidates = &mut self.candidates;
+            searcher.for_each_output(self.state, |pattern| {
+                let start = end - searcher.pattern_lens[pattern];
+                if start >= next_start {
+                    candidates.push(Reverse((start, searcher.rank(end, pattern), pattern, end)));
+                }
+            });
+        }
+    }
+}
+
+impl <'a, T> Iterator for AhoCorasickMatches<'a, T>
+    where T: Copy + Into<usize>
+{
+    type Item = (usize, usize);
+    fn next(&mut self) -> Option<(usize, usize)> {
+        if !self.overlapping_matches {
+            return self.next_leftmost();
+        }
+        while self.pending.is_empty() && self.position < self.haystack.len() {
+            let searcher = self.searcher;
+            self.state = searcher.next_state(self.state, self.haystack[self.position].into());
+            self.position += 1;
+            let end = self.position;
+            let pending = &mut self.pending;
+            searcher.for_each_output(self.state, |pattern| pending.push_back((end - searcher.pattern_lens[pattern], pattern)));
+        }
+        self.pending.pop_front()
+    }
+}
+
+
+pub struct AhoCorasickIter <'a, T: 'a> {
+    matches: AhoCorasickMatches<'a, T>,
+}
+
+impl <'a, T> Iterator for AhoCorasickIter<'a, T>
+    where T: Copy + Into<usize>
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        self.matches.next().map(|(position, _)| position)
+    }
+}
+
+
+/// An iterator over matches, which produces a `Match` for each one.
+pub struct AhoCorasickMatchIter <'a, T: 'a> {
+    matches: AhoCorasickMatches<'a, T>,
+}
+
+impl <'a, T> Iterator for AhoCorasickMatchIter<'a, T>
+    where T: Copy + Into<usize>
+{
+    type Item = Match;
+    fn next(&mut self) -> Option<Match> {
+        let searcher = self.matches.searcher;
+        self.matches.next().map(|(position, pattern)| {
+            Match::new(position, position + searcher.pattern_len(pattern)).with_pattern(pattern)
+        })
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+    use test_util::Random;
+
+    #[test]
+    pub fn test_overlapping() {
+        let needle = AhoCorasick::new(&[&b"he"[..], b"she", b"his", b"hers"]);
+        let haystack = b"ushers said his hershey";
+        assert_eq!(vec![(1, 1), (2, 0), (2, 3), (12, 2), (16, 0), (16, 3), (19, 1), (20, 0)],
+            needle.find_overlapping_patterns_in(haystack).collect::<Vec<_>>());
+        assert_eq!(vec![1, 2, 2, 12, 16, 16, 19, 20], needle.find_overlapping_in(haystack).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_matches() {
+        let needle = AhoCorasick::new(&[&b"he"[..], b"she", b"his", b"hers"]);
+        let haystack = b"ushers";
+        let found: Vec<_> = needle.find_overlapping_matches_in(haystack).map(|m| (m.range(), m.pattern())).collect();
+        assert_eq!(vec![(1 .. 4, 1), (2 .. 4, 0), (2 .. 6, 3)], found);
+        let found: Vec<_> = needle.find_matches_in(haystack).map(|m| m.slice(haystack)).collect();
+        assert_eq!(vec![&b"she"[..]], found);
+    }
+
+    #[test]
+    pub fn test_leftmost_first() {
+        let needle = AhoCorasick::with_match_kind(&["abcd", "b", "bcd", "c"], MatchKind::LeftmostFirst);
+        assert_eq!(vec![(1, 1), (2, 3)], needle.find_patterns_in(b"xbcdx").collect::<Vec<_>>());
+        let needle = AhoCorasick::with_match_kind(&["abc", "abcdef"], MatchKind::LeftmostFirst);
+        assert_eq!(vec![(0, 0)], needle.find_patterns_in(b"abcdef").collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_leftmost_longest() {
+        let needle = AhoCorasick::with_match_kind(&["abcd", "b", "bcd", "c"], MatchKind::LeftmostLongest);
+        assert_eq!(vec![(1, 2)], needle.find_patterns_in(b"xbcdx").collect::<Vec<_>>());
+        let needle = AhoCorasick::with_match_kind(&["abc", "abcdef"], MatchKind::LeftmostLongest);
+        assert_eq!(vec![(0, 1)], needle.find_patterns_in(b"abcdefabc").take(1).collect::<Vec<_>>());
+        assert_eq!(vec![0, 6], needle.find_in(b"abcdefabc").collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_leftmost_match_after_partial() {
+        // "abcd" fails part-way, and the leftmost match is a pattern that starts inside it
+        let needle = AhoCorasick::new(&["abcd", "bce"]);
+        assert_eq!(vec![(2, 1)], needle.find_patterns_in(b"zabce").collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_count_keywords() {
+        let needle = AhoCorasick::new(&["sea", "shore", "she"]);
+        let haystack = b"She sells sea shells on the sea shore.";
+        assert_eq!(4, needle.count_in(haystack));
+    }
+
+    #[test]
+    pub fn test_large_alphabet() {
+        let patterns: Vec<Vec<u16>> = vec![vec![1000, 2000], vec![2000, 3000]];
+        let needle = AhoCorasick::new(&patterns);
+        let haystack = [5, 1000, 2000, 3000, 1000];
+        assert_eq!(vec![(1, 0), (2, 1)], needle.find_overlapping_patterns_in(&haystack).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_empty_pattern() {
+        let needle = AhoCorasick::new(&["", "ab"]);
+        assert_eq!(vec![(0, 0), (1, 0), (0, 1), (2, 0)], needle.find_overlapping_patterns_in(b"ab").collect::<Vec<_>>());
+        assert_eq!(vec![0, 1, 2], needle.find_in(b"ab").collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_agrees_with_naive() {
+        let patterns = [&b"aba"[..], b"ab", b"bab", b"bbb", b"a"];
+        let needle = AhoCorasick::new(&patterns);
+        let mut random = Random::new(7);
+        let haystack: Vec<u8> = (0 .. 500).map(|_| b'a' + random.below(2) as u8).collect();
+        let mut expected = Vec::new();
+        for end in 1 .. haystack.len() + 1 {
+            let mut at_end: Vec<(usize, usize)> = patterns.iter().enumerate()
+                .filter(|&(_, p)| p.len() <= end && &haystack[end - p.len() .. end] == *p)
+                .map(|(i, p)| (end - p.len(), i))
+                .collect();
+            at_end.sort();
+            expected.extend(at_end);
+        }
+        assert_eq!(expected, needle.find_overlapping_patterns_in(&haystack).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_non_overlapping_agrees_with_naive() {
+        let patterns = [&b"ab"[..], b"babb", b"a", b"bab", b"abab"];
+        let mut random = Random::new(13);
+        let haystack: Vec<u8> = (0 .. 500).map(|_| b'a' + random.below(2) as u8).collect();
+        for &match_kind in &[MatchKind::LeftmostFirst, MatchKind::LeftmostLongest] {
+            let needle = AhoCorasick::with_match_kind(&patterns, match_kind);
+            let mut expected = Vec::new();
+            let mut position = 0;
+            while let Some((start, pattern)) = (position .. haystack.len()).filter_map(|start| {
+                let mut found = (0 .. patterns.len()).filter(|&i| haystack[start ..].starts_with(patterns[i]));
+                let pattern = match match_kind {
+                    MatchKind::LeftmostFirst => found.next(),
+                    MatchKind::LeftmostLongest => found.min_by_key(|&i| usize::MAX - patterns[i].len()),
+                };
+                pattern.map(|pattern| (start, pattern))
+            }).next() {
+                expected.push((start, pattern));
+                position = start + patterns[pattern].len();
+            }
+            assert_eq!(expected, needle.find_patterns_in(&haystack).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    pub fn test_short_pattern_prefixes_long_one() {
+        let long = vec![b'a'; 1000];
+        let patterns = [&b"a"[..], &long];
+        let haystack = vec![b'a'; 100_000];
+        let first = AhoCorasick::new(&patterns);
+        assert_eq!((0 .. 100_000).collect::<Vec<_>>(), first.find_in(&haystack).collect::<Vec<_>>());
+        let longest = AhoCorasick::with_match_kind(&patterns, MatchKind::LeftmostLongest);
+        let found: Vec<_> = longest.find_patterns_in(&haystack).collect();
+        assert_eq!((0 .. 100).map(|i| (i * 1000, 1)).collect::<Vec<_>>(), found);
+    }
+}
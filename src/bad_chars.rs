@@ -0,0 +1,157 @@
+//! Tables for the bad character rule, which map a symbol of the alphabet to the number of elements
+//! that can be skipped when that symbol is found in the haystack where it doesn't match the needle.
+//!
+//! Symbols are looked up by their `Into<usize>` value. `DenseTable` is a flat array and only
+//! accepts needles made of symbols below 256, `HashedTable` is a hash map that works for any
+//! alphabet, and `AutoTable` (the default for `BoyerMoore` and `Horspool`) uses a flat array for
+//! symbols below 256 and falls back to a hash map for any others. For byte alphabets the fallback
+//! is never taken, and the check for it is optimised away.
+use std::collections::HashMap;
+
+pub trait BadCharsTable {
+    /// Create a table for a needle of length `len`, in which every symbol maps to a skip of `len`.
+    fn new(len: usize) -> Self;
+
+    /// Set the number of elements that can be skipped when `c` is the bad character.
+    fn set(&mut self, c: usize, skip: usize);
+
+    /// The number of elements that can be skipped when `c` is the bad character.
+    fn get(&self, c: usize) -> usize;
+
+    /// Whether `c` can be stored in the table, and so can appear in a needle.
+    fn supports(_c: usize) -> bool {
+        true
+    }
+}
+
+
+/// A bad character table backed by a flat array. Symbols with a value of 256 or more can appear in
+/// the haystack, but not in the needle.
+pub struct DenseTable {
+    table: [usize; 256],
+    len: usize,
+}
+
+impl BadCharsTable for DenseTable {
+    fn new(len: usize) -> DenseTable {
+        DenseTable { table: [len; 256], len }
+    }
+
+    /// # Panics
+    /// If `c` is 256 or more.
+    fn set(&mut self, c: usize, skip: usize) {
+        assert!(c < 256, "symbol {} is too large for a DenseTable, use a HashedTable or AutoTable instead", c);
+        self.table[c] = skip;
+    }
+
+    #[inline]
+    fn get(&self, c: usize) -> usize {
+        self.table.get(c).cloned().unwrap_or(self.len)
+    }
+
+    fn supports(c: usize) -> bool {
+        c < 256
+    }
+}
+
+
+/// A bad character table backed by a hash map, for large alphabets.
+pub struct HashedTable {
+    table: HashMap<usize, usize>,
+    len: usize,
+}
+
+impl BadCharsTable for HashedTable {
+    fn new(len: usize) -> HashedTable {
+        HashedTable { table: HashMap::new(), len }
+    }
+
+    fn set(&mut self, c: usize, skip: usize) {
+        self.table.insert(c, skip);
+    }
+
+    #[inline]
+    fn get(&self, c: usize) -> usize {
+        self.table.get(&c).cloned().unwrap_or(self.len)
+    }
+}
+
+
+/// A bad character table which stores symbols below 256 in a flat array and any others in a hash map.
+pub struct AutoTable {
+    dense: DenseTable,
+    sparse: HashedTable,
+}
+
+impl BadCharsTable for AutoTable {
+    fn new(len: usize) -> AutoTable {
+        AutoTable { dense: DenseTable::new(len), sparse: HashedTable::new(len) }
+    }
+
+    fn set(&mut self, c: usize, skip: usize) {
+        if c < 256 {
+            self.dense.set(c, skip);
+        } else {
+            self.sparse.set(c, skip);
+        }
+    }
+
+    #[inline]
+    fn get(&self, c: usize) -> usize {
+        if c < 256 || self.sparse.table.is_empty() {
+            self.dense.get(c)
+        } else {
+            self.sparse.get(c)
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_dense_out_of_range() {
+        let mut table = DenseTable::new(5);
+        table.set(3, 1);
+        assert_eq!(1, table.get(3));
+        assert_eq!(5, table.get(4));
+        assert_eq!(5, table.get(70_000));
+    }
+
+    #[test]
+    pub fn test_supports() {
+        assert!(DenseTable::supports(255));
+        assert!(!DenseTable::supports(256));
+        assert!(HashedTable::supports(70_000));
+        assert!(AutoTable::supports(70_000));
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_dense_rejects_large_symbols() {
+        DenseTable::new(5).set(256, 1);
+    }
+
+    #[test]
+    pub fn test_auto() {
+        let mut table = AutoTable::new(5);
+        table.set(3, 1);
+        table.set(70_000, 2);
+        assert_eq!(1, table.get(3));
+        assert_eq!(2, table.get(70_000));
+        assert_eq!(5, table.get(70_001));
+        assert_eq!(5, table.get(4));
+    }
+
+    #[test]
+    pub fn test_hashed() {
+        let mut table = HashedTable::new(5);
+        table.set(3, 1);
+        table.set(70_000, 2);
+        assert_eq!(1, table.get(3));
+        assert_eq!(2, table.get(70_000));
+        assert_eq!(5, table.get(4));
+    }
+}
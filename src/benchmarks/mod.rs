@@ -2,10 +2,14 @@
 use test::Bencher;
 use super::BoyerMoore;
 use super::Horspool;
-use super::naive_search::NaiveSearch;
-use super::memchr_search::MemchrSearch;
-use super::boyer_moore_memchr::BoyerMooreMemchr;
-use super::horspool_memchr::HorspoolMemchr;
+use super::TwoWay;
+use super::ShiftOr;
+use super::SimdSearch;
+use super::Searcher;
+use super::NaiveSearch;
+use super::MemchrSearch;
+use super::BoyerMooreMemchr;
+use super::HorspoolMemchr;
 use super::{SearchIn, CountIn};
 mod pi_digits;
 mod alice;
@@ -38,10 +42,27 @@ macro_rules! bench_count (
     }
 );
 
+macro_rules! bench_count_overlapping (
+    ($name:ident, $searcher:expr, $test:expr) => {
+        #[bench]
+        fn $name(b: &mut Bencher) {
+            let haystack = $test.haystack;
+            let needle = $searcher($test.needle);
+            b.iter(|| {
+                assert_eq!($test.expected.unwrap(), needle.find_overlapping_in(&haystack).count())
+            });
+        }
+    }
+);
+
 bench_find_first!(find_pi_100k_digits_non_num_needle_BoyerMoore,        BoyerMoore::new,       non_num());
 bench_find_first!(find_pi_100k_digits_non_num_needle_BoyerMoore_memchr, BoyerMooreMemchr::new, non_num());
 bench_find_first!(find_pi_100k_digits_non_num_needle_Horspool_memchr,   HorspoolMemchr::new,   non_num());
 bench_find_first!(find_pi_100k_digits_non_num_needle_Horspool,          Horspool::new,         non_num());
+bench_find_first!(find_pi_100k_digits_non_num_needle_TwoWay,            TwoWay::new,           non_num());
+bench_find_first!(find_pi_100k_digits_non_num_needle_SimdSearch,        SimdSearch::new,       non_num());
+bench_find_first!(find_pi_100k_digits_non_num_needle_Searcher,          Searcher::new,         non_num());
+bench_find_first!(find_pi_100k_digits_non_num_needle_ShiftOr,           ShiftOr::new,          non_num());
 bench_find_first!(find_pi_100k_digits_non_num_needle_MemchrSearch,      MemchrSearch::new,     non_num());
 bench_find_first!(find_pi_100k_digits_non_num_needle_NaiveSearch,       NaiveSearch::new,      non_num());
 
@@ -49,6 +70,10 @@ bench_find_first!(find_pi_100k_digits_non_num_10_needle_BoyerMoore,        Boyer
 bench_find_first!(find_pi_100k_digits_non_num_10_needle_BoyerMoore_memchr, BoyerMooreMemchr::new, non_num_10());
 bench_find_first!(find_pi_100k_digits_non_num_10_needle_Horspool_memchr,   HorspoolMemchr::new,   non_num_10());
 bench_find_first!(find_pi_100k_digits_non_num_10_needle_Horspool,          Horspool::new,         non_num_10());
+bench_find_first!(find_pi_100k_digits_non_num_10_needle_TwoWay,            TwoWay::new,           non_num_10());
+bench_find_first!(find_pi_100k_digits_non_num_10_needle_SimdSearch,        SimdSearch::new,       non_num_10());
+bench_find_first!(find_pi_100k_digits_non_num_10_needle_Searcher,          Searcher::new,         non_num_10());
+bench_find_first!(find_pi_100k_digits_non_num_10_needle_ShiftOr,           ShiftOr::new,          non_num_10());
 bench_find_first!(find_pi_100k_digits_non_num_10_needle_MemchrSearch,      MemchrSearch::new,     non_num_10());
 bench_find_first!(find_pi_100k_digits_non_num_10_needle_NaiveSearch,       NaiveSearch::new,      non_num_10());
 
@@ -56,6 +81,9 @@ bench_find_first!(find_pi_100k_digits_non_num_100_needle_BoyerMoore,        Boye
 bench_find_first!(find_pi_100k_digits_non_num_100_needle_BoyerMoore_memchr, BoyerMooreMemchr::new, non_num_100());
 bench_find_first!(find_pi_100k_digits_non_num_100_needle_Horspool_memchr,   HorspoolMemchr::new,   non_num_100());
 bench_find_first!(find_pi_100k_digits_non_num_100_needle_Horspool,          Horspool::new,         non_num_100());
+bench_find_first!(find_pi_100k_digits_non_num_100_needle_TwoWay,            TwoWay::new,           non_num_100());
+bench_find_first!(find_pi_100k_digits_non_num_100_needle_SimdSearch,        SimdSearch::new,       non_num_100());
+bench_find_first!(find_pi_100k_digits_non_num_100_needle_Searcher,          Searcher::new,         non_num_100());
 bench_find_first!(find_pi_100k_digits_non_num_100_needle_MemchrSearch,      MemchrSearch::new,     non_num_100());
 bench_find_first!(find_pi_100k_digits_non_num_100_needle_NaiveSearch,       NaiveSearch::new,      non_num_100());
 
@@ -63,6 +91,9 @@ bench_find_first!(find_pi_100k_digits_BoyerMoore,          BoyerMoore::new,
 bench_find_first!(find_pi_100k_digits_BoyerMoore_memchr,   BoyerMooreMemchr::new, subsequence());
 bench_find_first!(find_pi_100k_digits_Horspool_memchr,     HorspoolMemchr::new,   subsequence());
 bench_find_first!(find_pi_100k_digits_Horspool,            Horspool::new,         subsequence());
+bench_find_first!(find_pi_100k_digits_TwoWay,              TwoWay::new,           subsequence());
+bench_find_first!(find_pi_100k_digits_SimdSearch,          SimdSearch::new,       subsequence());
+bench_find_first!(find_pi_100k_digits_Searcher,            Searcher::new,         subsequence());
 bench_find_first!(find_pi_100k_digits_MemchrSearch,        MemchrSearch::new,     subsequence());
 bench_find_first!(find_pi_100k_digits_NaiveSearch,         NaiveSearch::new,      subsequence());
 
@@ -70,6 +101,10 @@ bench_find_first!(find_pi_100k_digits_short_needle_BoyerMoore,        BoyerMoore
 bench_find_first!(find_pi_100k_digits_short_needle_BoyerMoore_memchr, BoyerMooreMemchr::new, subsequence_short());
 bench_find_first!(find_pi_100k_digits_short_needle_Horspool_memchr,   HorspoolMemchr::new,   subsequence_short());
 bench_find_first!(find_pi_100k_digits_short_needle_Horspool,          Horspool::new,         subsequence_short());
+bench_find_first!(find_pi_100k_digits_short_needle_TwoWay,            TwoWay::new,           subsequence_short());
+bench_find_first!(find_pi_100k_digits_short_needle_SimdSearch,        SimdSearch::new,       subsequence_short());
+bench_find_first!(find_pi_100k_digits_short_needle_Searcher,          Searcher::new,         subsequence_short());
+bench_find_first!(find_pi_100k_digits_short_needle_ShiftOr,           ShiftOr::new,          subsequence_short());
 bench_find_first!(find_pi_100k_digits_short_needle_MemchrSearch,      MemchrSearch::new,     subsequence_short());
 bench_find_first!(find_pi_100k_digits_short_needle_NaiveSearch,       NaiveSearch::new,      subsequence_short());
 
@@ -77,6 +112,10 @@ bench_count!(count_alice_box_BoyerMoore,        BoyerMoore::new,       count_ali
 bench_count!(count_alice_box_BoyerMoore_memchr, BoyerMooreMemchr::new, count_alice_box());
 bench_count!(count_alice_box_Horspool_memchr,   HorspoolMemchr::new,   count_alice_box());
 bench_count!(count_alice_box_Horspool,          Horspool::new,         count_alice_box());
+bench_count!(count_alice_box_TwoWay,            TwoWay::new,           count_alice_box());
+bench_count!(count_alice_box_SimdSearch,        SimdSearch::new,       count_alice_box());
+bench_count!(count_alice_box_Searcher,          Searcher::new,         count_alice_box());
+bench_count!(count_alice_box_ShiftOr,           ShiftOr::new,          count_alice_box());
 bench_count!(count_alice_box_MemchrSearch,      MemchrSearch::new,     count_alice_box());
 bench_count!(count_alice_box_NaiveSearch,       NaiveSearch::new,      count_alice_box());
 
@@ -84,6 +123,10 @@ bench_count!(count_alice_rabbit_BoyerMoore,        BoyerMoore::new,       count_
 bench_count!(count_alice_rabbit_BoyerMoore_memchr, BoyerMooreMemchr::new, count_alice_rabbit());
 bench_count!(count_alice_rabbit_Horspool_memchr,   HorspoolMemchr::new,   count_alice_rabbit());
 bench_count!(count_alice_rabbit_Horspool,          Horspool::new,         count_alice_rabbit());
+bench_count!(count_alice_rabbit_TwoWay,            TwoWay::new,           count_alice_rabbit());
+bench_count!(count_alice_rabbit_SimdSearch,        SimdSearch::new,       count_alice_rabbit());
+bench_count!(count_alice_rabbit_Searcher,          Searcher::new,         count_alice_rabbit());
+bench_count!(count_alice_rabbit_ShiftOr,           ShiftOr::new,          count_alice_rabbit());
 bench_count!(count_alice_rabbit_MemchrSearch,      MemchrSearch::new,     count_alice_rabbit());
 bench_count!(count_alice_rabbit_NaiveSearch,       NaiveSearch::new,      count_alice_rabbit());
 
@@ -91,9 +134,23 @@ bench_count!(count_alice_thoughtfully_BoyerMoore,        BoyerMoore::new,
 bench_count!(count_alice_thoughtfully_BoyerMoore_memchr, BoyerMooreMemchr::new, count_alice_thoughtfully());
 bench_count!(count_alice_thoughtfully_Horspool_memchr,   HorspoolMemchr::new,   count_alice_thoughtfully());
 bench_count!(count_alice_thoughtfully_Horspool,          Horspool::new,         count_alice_thoughtfully());
+bench_count!(count_alice_thoughtfully_TwoWay,            TwoWay::new,           count_alice_thoughtfully());
+bench_count!(count_alice_thoughtfully_SimdSearch,        SimdSearch::new,       count_alice_thoughtfully());
+bench_count!(count_alice_thoughtfully_Searcher,          Searcher::new,         count_alice_thoughtfully());
+bench_count!(count_alice_thoughtfully_ShiftOr,           ShiftOr::new,          count_alice_thoughtfully());
 bench_count!(count_alice_thoughtfully_MemchrSearch,      MemchrSearch::new,     count_alice_thoughtfully());
 bench_count!(count_alice_thoughtfully_NaiveSearch,       NaiveSearch::new,      count_alice_thoughtfully());
 
+bench_count_overlapping!(count_overlapping_periodic_BoyerMoore,        BoyerMoore::new,       count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_BoyerMoore_memchr, BoyerMooreMemchr::new, count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_Horspool_memchr,   HorspoolMemchr::new,   count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_Horspool,          Horspool::new,         count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_TwoWay,            TwoWay::new,           count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_SimdSearch,        SimdSearch::new,       count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_Searcher,          Searcher::new,         count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_MemchrSearch,      MemchrSearch::new,     count_overlapping_periodic());
+bench_count_overlapping!(count_overlapping_periodic_NaiveSearch,       NaiveSearch::new,      count_overlapping_periodic());
+
 struct Case <'a> {
     needle: &'a [u8],
     haystack: &'a [u8],
@@ -101,7 +158,7 @@ struct Case <'a> {
 }
 impl <'a> Case <'a> {
     fn new(needle: &'a [u8], haystack: &'a [u8], expected: Option<usize> ) -> Case<'a> {
-        Case { needle: needle, haystack: haystack, expected: expected }
+        Case { needle, haystack, expected }
     }
 }
 
@@ -115,6 +172,14 @@ fn subsequence_short<'a>() -> Case<'a> { Case::new(b"985750", pi_100k(), Some(99
 fn count_alice_box<'a>() -> Case<'a> { Case::new(b"box", alice(), Some(11)) }
 fn count_alice_rabbit<'a>() -> Case<'a> { Case::new(b"Rabbit", alice(), Some(45)) }
 fn count_alice_thoughtfully<'a>() -> Case<'a> { Case::new(b"thoughtfully", alice(), Some(4)) }
+fn count_overlapping_periodic<'a>() -> Case<'a> { Case::new(&PERIODIC[.. 100], &PERIODIC, Some(99_901)) }
+
+// "aaaa...ab", on which searchers that forget what they have already matched are quadratic
+static PERIODIC: [u8; 100_001] = {
+    let mut haystack = [b'a'; 100_001];
+    haystack[100_000] = b'b';
+    haystack
+};
 
 fn pi_100k<'a>() -> &'a [u8] { pi_100k_digits().as_bytes() }
 fn alice<'a>() -> &'a [u8] { alice_text().as_bytes() }
\ No newline at end of file
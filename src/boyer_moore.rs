@@ -1,4 +1,10 @@
-//! Implementation of the Boyer-Moore search algorithm, currently confined to `u8` arrays for input.
+//! Implementation of the Boyer-Moore search algorithm, for slices of any `Copy` type that can be
+//! converted `Into<usize>`. See the `bad_chars` module for how alphabets of more than 256 symbols are handled.
+//!
+//! When searching for overlapping matches, the needle is shifted by its period after each match,
+//! and the part of it that overlaps the previous match is not compared again (the Galil rule). This
+//! keeps the search linear for densely repeating needles such as `aaaa`, where it would otherwise
+//! compare every character of the needle at every position.
 //!
 //! # Examples
 //!
@@ -9,13 +15,28 @@
 //! assert_eq!(Some(11), needle.find_in(haystack).next());
 //! ```
 use std::cmp::max;
+use std::borrow::Cow;
+use bad_chars::{BadCharsTable, AutoTable};
+use error::NeedleError;
 use skip_search::*;
-use super::SearchIn;
-
-pub struct BoyerMoore <'a, T:'a> {
-    needle: &'a [T],
-    bad_chars: [usize; 256],
-    good_suffixes: Vec<usize>
+use std::io::Read;
+use iter_search::SkipSearchIter;
+use read_search::ReadSearchIter;
+use str_search::StrSearch;
+use std::iter::Rev;
+use std::ops::Range;
+use search_state::SearchState;
+use super::{SearchIn, MatchIn, ReverseSearchIn, SearchInIter, SearchInRead};
+
+pub struct BoyerMoore <'a, T:'a + Clone, B = AutoTable> {
+    needle: Cow<'a, [T]>,
+    bad_chars: B,
+    good_suffixes: Vec<usize>,
+    rev_bad_chars: B,
+    rev_good_suffixes: Vec<usize>,
+    // the smallest period of the needle, which is how far to shift after a match if matches may overlap
+    period: usize,
+    ascii_case_insensitive: bool,
 }
 
 impl <'a, T> BoyerMoore <'a, T>
@@ -24,20 +45,266 @@ impl <'a, T> BoyerMoore <'a, T>
     /// Construct a new Boyer-Moore search object, and pre-compute the skip tables.
     /// If you intend to search for the same needle in multiple haystacks, it is more
     /// efficient to create just one instance and then re-use it."
-    pub fn new(needle: &'a [T]) -> BoyerMoore<T> {
+    pub fn new(needle: &'a [T]) -> BoyerMoore<'a, T> {
+        BoyerMoore::from_cow(Cow::Borrowed(needle), false)
+    }
+
+    /// Construct a new Boyer-Moore search object which treats ASCII upper and lower case letters as
+    /// equal, both in the needle and in the haystack. Other symbols must match exactly.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchIn};
+    /// let needle = BoyerMoore::new_ascii_case_insensitive(b"content-length:");
+    /// let haystack = b"Host: example.com\r\nContent-Length: 42\r\n";
+    /// assert_eq!(Some(19), needle.find_first_in(haystack));
+    /// ```
+    pub fn new_ascii_case_insensitive(needle: &'a [T]) -> BoyerMoore<'a, T> {
+        BoyerMoore::from_cow(Cow::Borrowed(needle), true)
+    }
+
+    /// Construct a new Boyer-Moore search object, or return an error if the needle is empty.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, NeedleError};
+    /// assert!(BoyerMoore::try_new(b"abc").is_ok());
+    /// assert_eq!(Some(NeedleError::EmptyNeedle), BoyerMoore::try_new(b"").err());
+    /// ```
+    pub fn try_new(needle: &'a [T]) -> Result<BoyerMoore<'a, T>, NeedleError> {
+        BoyerMoore::try_with_table(needle)
+    }
+}
+
+impl <'a> BoyerMoore <'a, u8> {
+    /// Construct a new Boyer-Moore search object for searching in `str`s. The offsets that it finds
+    /// are always on char boundaries, so they can be used to index into the haystack.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchIn};
+    /// let needle = BoyerMoore::new_str("ñu");
+    /// let haystack = "El ñu come";
+    /// let position = needle.find_first_in(haystack).unwrap();
+    /// assert_eq!("ñu come", &haystack[position ..]);
+    /// ```
+    pub fn new_str(needle: &'a str) -> StrSearch<BoyerMoore<'a, u8>> {
+        StrSearch::new(BoyerMoore::new(needle.as_bytes()), needle.len())
+    }
+}
+impl <'a, T, B> BoyerMoore <'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    /// Construct a new Boyer-Moore search object, which uses a specific type of bad character table
+    /// rather than the default `AutoTable`. For example, a `HashedTable` uses much less memory
+    /// than the other tables when the needle is short and the alphabet is large.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, HashedTable, SearchIn};
+    /// let code_units: Vec<u16> = "ŝearch ĥere".encode_utf16().collect();
+    /// let needle: BoyerMoore<u16, HashedTable> = BoyerMoore::with_table(&code_units[7 ..]);
+    /// assert_eq!(Some(7), needle.find_first_in(&code_units));
+    /// ```
+    pub fn with_table(needle: &'a [T]) -> BoyerMoore<'a, T, B> {
+        BoyerMoore::from_cow(Cow::Borrowed(needle), false)
+    }
+
+    /// Construct a new Boyer-Moore search object with a specific type of bad character table, or return
+    /// an error if the needle is empty or contains a symbol that the table can't store.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, DenseTable, NeedleError};
+    /// let needle: Result<BoyerMoore<u16, DenseTable>, _> = BoyerMoore::try_with_table(&[1, 2, 300]);
+    /// assert_eq!(Some(NeedleError::UnsupportedSymbol(300)), needle.err());
+    /// ```
+    pub fn try_with_table(needle: &'a [T]) -> Result<BoyerMoore<'a, T, B>, NeedleError> {
+        check_needle::<T, B>(needle)?;
+        Ok(BoyerMoore::with_table(needle))
+    }
+
+    fn from_cow(needle: Cow<'a, [T]>, ascii_case_insensitive: bool) -> BoyerMoore<'a, T, B> {
+        let (good_suffixes, rev_good_suffixes, period) = if ascii_case_insensitive {
+            let folded: Vec<usize> = needle.iter().map(|&c| fold_ascii_case(c.into())).collect();
+            (build_good_suffixes_table(&folded), build_rev_good_suffixes_table(&folded), get_period(&folded))
+        } else {
+            (build_good_suffixes_table(&needle), build_rev_good_suffixes_table(&needle), get_period(&needle))
+        };
         BoyerMoore { 
-            needle: needle,
-            bad_chars: build_bad_chars_table(&needle),
-            good_suffixes: build_good_suffixes_table(&needle)
+            bad_chars: build_bad_chars_table(&needle, ascii_case_insensitive),
+            good_suffixes,
+            rev_bad_chars: build_rev_bad_chars_table(&needle, ascii_case_insensitive),
+            rev_good_suffixes,
+            period,
+            needle,
+            ascii_case_insensitive,
+        }
+    }
+
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[T] {
+        &self.needle
+    }
+
+    /// Whether ASCII upper and lower case letters are treated as equal.
+    pub fn is_ascii_case_insensitive(&self) -> bool {
+        self.ascii_case_insensitive
+    }
+
+    /// Convert into a search object that owns its needle, copying the needle if it was borrowed.
+    /// The skip tables are moved across rather than being computed again.
+    pub fn into_owned(self) -> BoyerMoore<'static, T, B> {
+        BoyerMoore {
+            needle: Cow::Owned(self.needle.into_owned()),
+            ..self
+        }
+    }
+
+    /// Returns an iterator that will produce the indices of the needle within `range` of the
+    /// haystack. Only matches which lie entirely within the range are found, and the indices are
+    /// offsets into the whole haystack. This iterator will not find overlapping matches.
+    ///
+    /// # Panics
+    /// If the range is not within the haystack.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::BoyerMoore;
+    /// let needle = BoyerMoore::new(b"ab");
+    /// let haystack = b"ab ab ab ab";
+    /// assert_eq!(vec![3, 6], needle.find_in_range(haystack, 2 .. 10).collect::<Vec<usize>>());
+    /// ```
+    pub fn find_in_range<'b>(&'b self, haystack: &'b [T], range: Range<usize>) -> BoyerMooreIter<'b, T, B> {
+        check_range(&range, haystack.len());
+        self.iter(haystack, range.start, range.end, false)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle within `range` of the
+    /// haystack, as offsets into the whole haystack. This iterator will find overlapping matches.
+    ///
+    /// # Panics
+    /// If the range is not within the haystack.
+    pub fn find_overlapping_in_range<'b>(&'b self, haystack: &'b [T], range: Range<usize>) -> BoyerMooreIter<'b, T, B> {
+        check_range(&range, haystack.len());
+        self.iter(haystack, range.start, range.end, true)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// at `start`. This iterator will not find overlapping matches.
+    ///
+    /// # Panics
+    /// If `start` is past the end of the haystack.
+    pub fn find_from<'b>(&'b self, haystack: &'b [T], start: usize) -> BoyerMooreIter<'b, T, B> {
+        check_range(&(start .. haystack.len()), haystack.len());
+        self.iter(haystack, start, haystack.len(), false)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// at `start`. This iterator will find overlapping matches.
+    ///
+    /// # Panics
+    /// If `start` is past the end of the haystack.
+    pub fn find_overlapping_from<'b>(&'b self, haystack: &'b [T], start: usize) -> BoyerMooreIter<'b, T, B> {
+        check_range(&(start .. haystack.len()), haystack.len());
+        self.iter(haystack, start, haystack.len(), true)
+    }
+
+    /// Carry on a search from a `SearchState`, which was saved from an iterator of this searcher
+    /// over the same haystack. The search carries on from the saved position up to the saved end,
+    /// finding overlapping matches only if the saved iterator did. The part of the needle already
+    /// known to match after an overlapping match is carried over too, so it isn't compared again.
+    /// A state is only valid for the same needle: with any other, matches may be missed or wrongly
+    /// reported.
+    ///
+    /// # Panics
+    /// If the state refers to offsets past the end of the haystack.
+    pub fn resume_in<'b>(&'b self, haystack: &'b [T], state: SearchState) -> BoyerMooreIter<'b, T, B> {
+        // only the end is checked, as a finished search may have moved its position past the end
+        assert!(state.end <= haystack.len(), "the search state is for a longer haystack");
+        // the remembered part of the needle must be shorter than it, or it can't be from this
+        // needle, so forget it and compare the whole needle again
+        let len = self.needle.len();
+        BoyerMooreIter {
+            memory: if state.memory < len { state.memory } else { 0 },
+            rev_memory: if state.rev_memory < len { state.rev_memory } else { 0 },
+            ..self.iter(haystack, state.position, state.end, state.overlapping_matches)
         }
     }
-}
 
+    fn iter<'b>(&'b self, haystack: &'b [T], position: usize, end: usize, overlapping_matches: bool) -> BoyerMooreIter<'b, T, B> {
+        BoyerMooreIter {
+            searcher: self,
+            haystack,
+            position,
+            end,
+            memory: 0,
+            rev_memory: 0,
+            overlapping_matches,
+        }
+    }
+}
 
-impl <'a, T> SearchIn<'a, [T]> for BoyerMoore<'a, T>
+impl <T> BoyerMoore <'static, T>
     where T: Copy + PartialEq + Into<usize>
 {
-    type Iter = BoyerMooreIter<'a, T>;
+    /// Construct a new Boyer-Moore search object which owns its needle. Unlike one created with
+    /// `new`, it does not borrow anything, so it can be kept in a long-lived struct or a static
+    /// and used to search in haystacks of any lifetime.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchIn};
+    /// struct Config { separator: BoyerMoore<'static, u8> }
+    /// let config = Config { separator: BoyerMoore::from_vec(b"\r\n\r\n".to_vec()) };
+    /// let buffer = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+    /// assert_eq!(Some(33), config.separator.find_first_in(&buffer));
+    /// ```
+    pub fn from_vec(needle: Vec<T>) -> BoyerMoore<'static, T> {
+        BoyerMoore::from_cow(Cow::Owned(needle), false)
+    }
+
+    /// Construct a new Boyer-Moore search object which owns its needle, and treats ASCII upper and
+    /// lower case letters as equal, like one created with `new_ascii_case_insensitive`.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchIn};
+    /// let header = BoyerMoore::from_vec_ascii_case_insensitive(b"content-length:".to_vec());
+    /// let haystack = b"Host: example.com\r\nContent-Length: 42\r\n".to_vec();
+    /// assert_eq!(Some(19), header.find_first_in(&haystack));
+    /// ```
+    pub fn from_vec_ascii_case_insensitive(needle: Vec<T>) -> BoyerMoore<'static, T> {
+        BoyerMoore::from_cow(Cow::Owned(needle), true)
+    }
+}
+
+impl <T, B> BoyerMoore <'static, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    /// Construct a new Boyer-Moore search object which owns its needle, and uses a specific type of
+    /// bad character table, like one created with `with_table`.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, HashedTable, SearchIn};
+    /// let code_units: Vec<u16> = "ŝearch ĥere".encode_utf16().collect();
+    /// let needle: BoyerMoore<u16, HashedTable> = BoyerMoore::from_vec_with_table(code_units[7 ..].to_vec());
+    /// assert_eq!(Some(7), needle.find_first_in(&code_units));
+    /// ```
+    pub fn from_vec_with_table(needle: Vec<T>) -> BoyerMoore<'static, T, B> {
+        BoyerMoore::from_cow(Cow::Owned(needle), false)
+    }
+}
+
+
+impl <'a, T, B> SearchIn<[T]> for BoyerMoore<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    type Iter<'b> = BoyerMooreIter<'b, T, B> where Self: 'b;
 
     /// Returns an iterator that will produce the indices of the needle in the haystack.
     /// This iterator will not find overlapping matches; the first character of a match 
@@ -50,13 +317,8 @@ impl <'a, T> SearchIn<'a, [T]> for BoyerMoore<'a, T>
     /// let haystack = b"aabaabaabaabaaba";
     /// assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
     /// ```
-    fn find_in(&'a self, haystack: &'a [T]) -> BoyerMooreIter<'a, T> {
-        BoyerMooreIter {
-            searcher: &self,
-            haystack: haystack,
-            position: 0,
-            overlapping_matches: false,
-        }
+    fn find_in<'b>(&'b self, haystack: &'b [T]) -> BoyerMooreIter<'b, T, B> {
+        self.iter(haystack, 0, haystack.len(), false)
     }
 
     /// Returns an iterator that will produce the indices of the needle in the haystack.
@@ -70,23 +332,140 @@ impl <'a, T> SearchIn<'a, [T]> for BoyerMoore<'a, T>
     /// let haystack = b"aabaabaabaabaaba";
     /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
     /// ```
-    fn find_overlapping_in(&'a self, haystack: &'a [T]) -> BoyerMooreIter<'a, T> {
-        BoyerMooreIter {
-            searcher: &self,
-            haystack: &haystack,
-            position: 0,
-            overlapping_matches: true
-        }
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> BoyerMooreIter<'b, T, B> {
+        self.iter(haystack, 0, haystack.len(), true)
+    }
+}
+
+impl <'a, T, B> MatchIn<T> for BoyerMoore<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    fn match_len(&self) -> usize {
+        self.needle.len()
     }
 }
 
 
-impl <'a, T> SkipSearch<T> for &'a BoyerMoore <'a, T>
-    where T: Copy + Into<usize>
+impl <'a, T, B> ReverseSearchIn<[T]> for BoyerMoore<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    type RevIter<'b> = Rev<BoyerMooreIter<'b, T, B>> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// from the end. This iterator will not find overlapping matches; the last character of a match
+    /// will come before the first character of the previously found match. Note that if the needle
+    /// can overlap with itself, these may not be the same matches that `find_in` finds.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, ReverseSearchIn};
+    /// let needle = BoyerMoore::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![12,6,0], needle.rfind_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn rfind_in<'b>(&'b self, haystack: &'b [T]) -> Rev<BoyerMooreIter<'b, T, B>> {
+        self.find_in(haystack).rev()
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// from the end. This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, ReverseSearchIn};
+    /// let needle = BoyerMoore::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![12,9,6,3,0], needle.rfind_overlapping_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn rfind_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> Rev<BoyerMooreIter<'b, T, B>> {
+        self.find_overlapping_in(haystack).rev()
+    }
+}
+
+impl <'a, T, B, I> SearchInIter<I> for BoyerMoore<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable,
+          I: IntoIterator<Item = T>
+{
+    type Iter<'b> = SkipSearchIter<&'b BoyerMoore<'b, T, B>, I::IntoIter> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the elements of
+    /// `haystack`, consuming it as it goes. This iterator will not find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchInIter};
+    /// let needle = BoyerMoore::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba".iter().cloned();
+    /// assert_eq!(vec![0,6,12], needle.find_in_iter(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_in_iter(&self, haystack: I) -> Self::Iter<'_> {
+        SkipSearchIter::new(self, haystack.into_iter(), false)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the elements of
+    /// `haystack`, consuming it as it goes. This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchInIter};
+    /// let needle = BoyerMoore::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba".iter().cloned();
+    /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in_iter(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in_iter(&self, haystack: I) -> Self::Iter<'_> {
+        SkipSearchIter::new(self, haystack.into_iter(), true)
+    }
+}
+
+
+impl <'a, B, R> SearchInRead<R> for BoyerMoore<'a, u8, B>
+    where B: BadCharsTable,
+          R: Read
+{
+    type Iter<'b> = ReadSearchIter<&'b BoyerMoore<'b, u8, B>, R> where Self: 'b;
+
+    /// Returns an iterator that will produce the byte offsets of the needle in the stream read
+    /// from `reader`. This iterator will not find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchInRead};
+    /// let needle = BoyerMoore::new(b"aaba");
+    /// let reader = &b"aabaabaabaabaaba"[..];
+    /// let found: Vec<usize> = needle.find_in_read(reader).map(Result::unwrap).collect();
+    /// assert_eq!(vec![0,6,12], found);
+    /// ```
+    fn find_in_read(&self, reader: R) -> Self::Iter<'_> {
+        ReadSearchIter::new(self, reader, false)
+    }
+
+    /// Returns an iterator that will produce the byte offsets of the needle in the stream read
+    /// from `reader`. This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMoore, SearchInRead};
+    /// let needle = BoyerMoore::new(b"aaba");
+    /// let reader = &b"aabaabaabaabaaba"[..];
+    /// let found: Vec<usize> = needle.find_overlapping_in_read(reader).map(Result::unwrap).collect();
+    /// assert_eq!(vec![0,3,6,9,12], found);
+    /// ```
+    fn find_overlapping_in_read(&self, reader: R) -> Self::Iter<'_> {
+        ReadSearchIter::new(self, reader, true)
+    }
+}
+
+
+impl <'a, T, B> SkipSearch<T> for &'a BoyerMoore <'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
 {
     #[inline]
-    default fn skip_offset(&self, bad_char: T, needle_position: usize, _haystack: &[T], _haystack_position: usize) -> usize {
-        max(self.bad_chars[bad_char.into()], self.good_suffixes[needle_position])
+    fn skip_offset(&self, bad_char: T, needle_position: usize, _haystack: &[T], _haystack_position: usize) -> usize {
+        max(self.bad_chars.get(bad_char.into()), self.good_suffixes[needle_position])
     }
 
     #[inline]
@@ -95,33 +474,102 @@ impl <'a, T> SkipSearch<T> for &'a BoyerMoore <'a, T>
     }
 
     #[inline]
-    fn char_at(&self, index: usize) -> T {
-        self.needle[index]
+    fn matches_at(&self, index: usize, c: T) -> bool {
+        symbols_match(self.needle[index], c, self.ascii_case_insensitive)
     }
 }
 
 
+impl <'a, T, B> ReverseSkipSearch<T> for &'a BoyerMoore <'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    #[inline]
+    fn rev_skip_offset(&self, bad_char: T, needle_position: usize, _haystack: &[T], _haystack_position: usize) -> usize {
+        max(self.rev_bad_chars.get(bad_char.into()), self.rev_good_suffixes[needle_position])
+    }
+}
+
 
-pub struct BoyerMooreIter <'a, T:'a> {
-    searcher: &'a BoyerMoore<'a, T>,
+
+pub struct BoyerMooreIter <'a, T:'a + Clone, B:'a = AutoTable> {
+    searcher: &'a BoyerMoore<'a, T, B>,
     haystack: &'a [T],
     position: usize,
+    end: usize,
+    // After an overlapping match, the needle is shifted by its period, and the part of it which
+    // overlaps the previous match is already known to match. This is the length of that part, from
+    // the start of the needle (going forwards) or the end of it (going backwards).
+    memory: usize,
+    rev_memory: usize,
     overlapping_matches: bool,
 }
 
-impl <'a, T> Iterator for BoyerMooreIter<'a, T> 
-    where T: Copy + PartialEq + Into<usize>
+impl <'a, T: 'a + Clone, B> BoyerMooreIter<'a, T, B> {
+    /// Save the progress of the search, so that it can be carried on later with `resume_in`.
+    pub fn state(&self) -> SearchState {
+        SearchState {
+            position: self.position,
+            end: self.end,
+            memory: self.memory,
+            rev_memory: self.rev_memory,
+            overlapping_matches: self.overlapping_matches,
+        }
+    }
+}
+
+impl <'a, T, B> Iterator for BoyerMooreIter<'a, T, B> 
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
 {
     type Item = usize;
     fn next(&mut self) -> Option<usize> {
-        find_from_position(&self.searcher, &self.haystack, self.position)
-            .and_then(|position| {
-                if self.overlapping_matches {
-                    self.position = position + 1;
+        if self.end < self.searcher.needle.len() {
+            return None;
+        }
+        let memory = self.memory;
+        self.memory = 0;
+        find_from_position_with_memory(&self.searcher, &self.haystack[.. self.end], self.position, memory)
+            .inspect(|&position| {
+                let len = self.searcher.needle.len();
+                if self.overlapping_matches && len > 0 {
+                    self.position = position + self.searcher.period;
+                    self.memory = len - self.searcher.period;
+                } else {
+                    self.position = position_after_match(position, len, self.overlapping_matches);
+                }
+            })
+    }
+}
+
+impl <'a, T, B> DoubleEndedIterator for BoyerMooreIter<'a, T, B> 
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    fn next_back(&mut self) -> Option<usize> {
+        if self.position > self.end {
+            return None;
+        }
+        let start = self.position;
+        let memory = self.rev_memory;
+        self.rev_memory = 0;
+        rfind_from_position_with_memory(&self.searcher, &self.haystack[start ..], self.end - start, memory)
+            .map(|position| position + start)
+            .inspect(|&position| {
+                let len = self.searcher.needle.len();
+                if len == 0 {
+                    // an empty needle matches at every position, so move back past this one
+                    if position == 0 {
+                        self.position = 1;
+                    } else {
+                        self.end = position - 1;
+                    }
+                } else if self.overlapping_matches {
+                    self.end = position + len - self.searcher.period;
+                    self.rev_memory = len - self.searcher.period;
                 } else {
-                    self.position = position + self.searcher.needle.len();
+                    self.end = position;
                 }
-                Some(position)
             })
     }
 }
@@ -130,7 +578,9 @@ impl <'a, T> Iterator for BoyerMooreIter<'a, T>
 #[cfg(test)]
 pub mod test {
     use super::*;
-    use super::super::{SearchIn, CountIn};
+    use bad_chars::{DenseTable, HashedTable};
+    use super::super::{SearchIn, ReverseSearchIn, CountIn};
+    use test_util::{Random, naive_find};
 
     #[test]
     pub fn test_simple() {
@@ -178,6 +628,13 @@ pub mod test {
         assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
     }
 
+    #[test]
+    pub fn test_overlapping_periodic_needle() {
+        let needle = BoyerMoore::new(b"babab");
+        let haystack = b"xabababab";
+        assert_eq!(vec![2,4], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    }
+
     #[test]
     pub fn test_non_overlapping() {
         let needle = BoyerMoore::new(b"aaba");
@@ -189,7 +646,7 @@ pub mod test {
     pub fn test_occurs_in() {
         let needle = BoyerMoore::new(b"abc");
         let haystack = b"xxxxxxabcxxxxabc";
-        assert_eq!(true, needle.occurs_in(haystack));
+        assert!(needle.occurs_in(haystack));
     }
 
 
@@ -197,7 +654,7 @@ pub mod test {
     pub fn test_not_occurs_in() {
         let needle = BoyerMoore::new(b"abc");
         let haystack = b"xxxxxxabacxxxxaba";
-        assert_eq!(false, needle.occurs_in(haystack));
+        assert!(!needle.occurs_in(haystack));
     }
 
 
@@ -207,4 +664,236 @@ pub mod test {
         let haystack = b"She sells sea shells on the sea shore.";
         assert_eq!(2, needle.count_in(haystack));
     }
+
+    #[test]
+    pub fn test_owned_needle() {
+        struct Config {
+            separator: BoyerMoore<'static, u8>,
+        }
+        let config = Config { separator: BoyerMoore::from_vec(b"\r\n\r\n".to_vec()) };
+        for request in &["GET / HTTP/1.1\r\n\r\n", "HEAD /\r\n\r\n"] {
+            let buffer = request.as_bytes().to_vec();
+            assert_eq!(Some(buffer.len() - 4), config.separator.find_first_in(&buffer));
+        }
+    }
+
+    #[test]
+    pub fn test_owned_needle_variants() {
+        let haystack = b"Warn: WARN warn".to_vec();
+        let needle = BoyerMoore::from_vec_ascii_case_insensitive(b"warn".to_vec());
+        assert!(needle.is_ascii_case_insensitive());
+        assert_eq!(vec![0, 6, 11], needle.find_in(&haystack).collect::<Vec<usize>>());
+        let needle: BoyerMoore<u8, HashedTable> = BoyerMoore::from_vec_with_table(b"warn".to_vec());
+        assert_eq!(vec![11], needle.find_in(&haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_into_owned() {
+        let needle = {
+            let bytes = b"xyz".to_vec();
+            BoyerMoore::new(&bytes).into_owned()
+        };
+        let haystack = b"01xyzxyz890xyz45xyz";
+        assert_eq!(b"xyz", needle.needle());
+        assert_eq!(vec![2,5,11,16], needle.find_in(haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_generic_searcher() {
+        fn count_lines<S: CountIn<[u8]>>(searcher: &S, text: &str) -> usize {
+            let buffer = text.as_bytes().to_vec();
+            searcher.count_in(&buffer)
+        }
+        let newline = BoyerMoore::from_vec(vec![b'\n']);
+        assert_eq!(2, count_lines(&newline, "one\ntwo\nthree"));
+    }
+
+    #[test]
+    pub fn test_find_last_in() {
+        let needle = BoyerMoore::new(b"\r\n\r\n");
+        let haystack = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n\r\nbody";
+        assert_eq!(Some(40), needle.find_last_in(haystack));
+        assert_eq!(None, needle.find_last_in(b"\r\n"));
+    }
+
+    #[test]
+    pub fn test_rfind_in() {
+        let needle = BoyerMoore::new(b"xyz");
+        let haystack = b"01xyzxyz890xyz45xyz";
+        assert_eq!(vec![16,11,5,2], needle.rfind_in(haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_rfind_overlapping_in() {
+        let needle = BoyerMoore::new(b"aa");
+        let haystack = b"aaaaa";
+        assert_eq!(vec![3,2,1,0], needle.rfind_overlapping_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(vec![3,1], needle.rfind_in(haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_double_ended() {
+        let needle = BoyerMoore::new(b"xyz");
+        let haystack = b"01xyzxyz890xyz45xyz";
+        let mut iter = needle.find_in(haystack);
+        assert_eq!(Some(2), iter.next());
+        assert_eq!(Some(16), iter.next_back());
+        assert_eq!(Some(11), iter.next_back());
+        assert_eq!(Some(5), iter.next());
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter.next_back());
+    }
+
+    #[test]
+    pub fn test_rfind_agrees_with_find() {
+        // a simple deterministic pseudo-random sequence over a small alphabet, to get lots of partial matches
+        let mut random = Random::new(12345);
+        let haystack: Vec<u8> = (0 .. 2000).map(|_| b'a' + random.below(3) as u8).collect();
+        for needle in &[&b"ab"[..], b"aba", b"cabca", b"aaaa", b"bcbcb"] {
+            let needle = BoyerMoore::new(needle);
+            let mut forwards: Vec<usize> = needle.find_overlapping_in(&haystack).collect();
+            forwards.reverse();
+            assert_eq!(forwards, needle.rfind_overlapping_in(&haystack).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    pub fn test_large_alphabet() {
+        let haystack: Vec<u16> = "the ĉapitalo of Ĉinio".encode_utf16().collect();
+        let needle: Vec<u16> = "Ĉinio".encode_utf16().collect();
+        assert_eq!(Some(16), BoyerMoore::new(&needle).find_first_in(&haystack));
+        let needle: BoyerMoore<u16, HashedTable> = BoyerMoore::with_table(&needle);
+        assert_eq!(Some(16), needle.find_first_in(&haystack));
+    }
+
+    #[test]
+    pub fn test_dense_table_with_large_haystack_symbols() {
+        let haystack: Vec<u16> = vec![1000, 2, 3, 1000, 1, 2, 3, 4000];
+        let needle: BoyerMoore<u16, DenseTable> = BoyerMoore::with_table(&[1, 2, 3]);
+        assert_eq!(Some(4), needle.find_first_in(&haystack));
+    }
+
+    #[test]
+    pub fn test_token_ids() {
+        #[derive(Copy, Clone, PartialEq, Debug)]
+        struct Token(u32);
+        impl From<Token> for usize {
+            fn from(token: Token) -> usize { token.0 as usize }
+        }
+        let haystack: Vec<Token> = [70_000, 3, 70_001, 70_000, 70_001, 9].iter().map(|&t| Token(t)).collect();
+        let needle = BoyerMoore::new(&haystack[3 .. 5]);
+        assert_eq!(vec![3], needle.find_in(&haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_ascii_case_insensitive() {
+        let needle = BoyerMoore::new_ascii_case_insensitive(b"WaRn");
+        let haystack = b"INFO start\nwarn low disk\nWARN low disk\nwarning";
+        assert_eq!(vec![11, 25, 39], needle.find_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(Some(39), needle.find_last_in(haystack));
+        assert_eq!(3, needle.count_in(&haystack[..]));
+        assert_eq!(None, BoyerMoore::new(b"WaRn").find_first_in(haystack));
+    }
+
+    #[test]
+    pub fn test_ascii_case_insensitive_only_folds_letters() {
+        // '@' and '`' differ from 'A' and 'a' by the same bit as the case of a letter
+        let needle = BoyerMoore::new_ascii_case_insensitive(b"a@");
+        assert_eq!(vec![2], needle.find_in(b"a`A@").collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_ascii_case_insensitive_agrees_with_naive() {
+        let mut random = Random::new(54321);
+        let haystack: Vec<u8> = (0 .. 2000).map(|_| b"abAB"[random.below(4) as usize]).collect();
+        for needle in &[&b"Ab"[..], b"aBa", b"BaaB", b"aaaa", b"babAB"] {
+            let expected = naive_find(&haystack, needle.len(), |window| window.eq_ignore_ascii_case(needle));
+            let needle = BoyerMoore::new_ascii_case_insensitive(needle);
+            assert_eq!(expected, needle.find_overlapping_in(&haystack).collect::<Vec<usize>>());
+            assert_eq!(expected.iter().rev().cloned().collect::<Vec<usize>>(), needle.rfind_overlapping_in(&haystack).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    pub fn test_periodic_overlapping() {
+        let haystack = b"aaaaaaaaaabaaaaabaabaabaab";
+        for needle in &[&b"aaa"[..], b"aab", b"abaab", b"aabaab", b"baabaa"] {
+            let expected = naive_find(haystack, needle.len(), |window| window == *needle);
+            let needle = BoyerMoore::new(needle);
+            assert_eq!(expected, needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+            let mut reversed = expected.clone();
+            reversed.reverse();
+            assert_eq!(reversed, needle.rfind_overlapping_in(haystack).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    pub fn test_double_ended_periodic() {
+        let needle = BoyerMoore::new(b"aa");
+        let mut iter = needle.find_overlapping_in(b"aaaaaa");
+        assert_eq!(Some(0), iter.next());
+        assert_eq!(Some(4), iter.next_back());
+        assert_eq!(Some(1), iter.next());
+        assert_eq!(Some(3), iter.next_back());
+        assert_eq!(Some(2), iter.next());
+        assert_eq!(None, iter.next_back());
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    pub fn test_find_in_range() {
+        let needle = BoyerMoore::new(b"aba");
+        let haystack = b"abababa aba";
+        assert_eq!(vec![2, 4], needle.find_overlapping_in_range(haystack, 1 .. 7).collect::<Vec<usize>>());
+        assert_eq!(vec![4, 2], needle.find_overlapping_in_range(haystack, 1 .. 7).rev().collect::<Vec<usize>>());
+        assert_eq!(vec![2, 8], needle.find_in_range(haystack, 1 .. 11).collect::<Vec<usize>>());
+        assert_eq!(vec![4, 8], needle.find_from(haystack, 3).collect::<Vec<usize>>());
+        assert_eq!(vec![2, 4, 8], needle.find_overlapping_from(haystack, 1).collect::<Vec<usize>>());
+        assert_eq!(None, needle.find_in_range(haystack, 8 .. 10).next());
+        let empty = BoyerMoore::new(b"");
+        assert_eq!(vec![3, 4, 5], empty.find_in_range(haystack, 3 .. 5).collect::<Vec<usize>>());
+        assert_eq!(vec![5, 4, 3], empty.find_in_range(haystack, 3 .. 5).rev().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_range_outside_haystack() {
+        BoyerMoore::new(b"a").find_in_range(b"abc", 2 .. 4);
+    }
+
+    #[test]
+    pub fn test_resume() {
+        let needle = BoyerMoore::new(b"aa");
+        let haystack = b"aaaaaa";
+        let mut iter = needle.find_overlapping_in(haystack);
+        assert_eq!(Some(0), iter.next());
+        assert_eq!(Some(4), iter.next_back());
+        let state = iter.state();
+        assert_eq!((1, 5, true), (state.position(), state.end(), state.is_overlapping()));
+        assert_eq!(vec![1, 2, 3], needle.resume_in(haystack, state).collect::<Vec<usize>>());
+        // a finished search stays finished
+        let mut iter = needle.find_in(haystack);
+        assert_eq!(3, iter.by_ref().count());
+        assert_eq!(None, needle.resume_in(haystack, iter.state()).next());
+        let empty = BoyerMoore::new(b"");
+        let mut iter = empty.find_in(b"ab");
+        assert_eq!(3, iter.by_ref().count());
+        assert_eq!(None, empty.resume_in(b"ab", iter.state()).next());
+    }
+
+    #[test]
+    pub fn test_resume_with_longer_needle_state() {
+        // after an overlapping match of "aaaa", three symbols are remembered, which is more than
+        // the whole of "ab"
+        let haystack = b"aaaaaxab";
+        let longer = BoyerMoore::new(b"aaaa");
+        let mut iter = longer.find_overlapping_in(haystack);
+        assert_eq!(Some(0), iter.next());
+        let needle = BoyerMoore::new(b"ab");
+        assert_eq!(vec![6], needle.resume_in(haystack, iter.state()).collect::<Vec<usize>>());
+        let haystack = b"abaaaaa";
+        let mut iter = longer.find_overlapping_in(haystack);
+        assert_eq!(Some(3), iter.next_back());
+        assert_eq!(vec![0], needle.resume_in(haystack, iter.state()).rev().collect::<Vec<usize>>());
+    }
 }
@@ -1,58 +1,111 @@
-use memchr::memchr;
+//! Boyer-Moore for byte slices, with a `memchr` prefilter. Whenever the skip tables would move the
+//! needle by at least its own length, the search instead uses `memchr` to jump to the next place
+//! where the rarest bytes of the needle line up with the haystack. This makes it much faster than
+//! `BoyerMoore` when those bytes are rare in the haystack, and the good suffix rule keeps the
+//! worst case in check when they are not.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{BoyerMooreMemchr, SearchIn};
+//! let needle = BoyerMooreMemchr::new(b"example");
+//! let haystack = b"This is an example of searching for a word";
+//! assert_eq!(Some(11), needle.find_first_in(haystack));
+//! ```
 use std::cmp::max;
+use bad_chars::{BadCharsTable, DenseTable};
 use skip_search::*;
-use super::SearchIn;
+use rare_bytes::RareBytes;
+use super::{SearchIn, MatchIn};
 
 pub struct BoyerMooreMemchr <'a> {
     needle: &'a [u8],
-    bad_chars: [usize; 256],
-    good_suffixes: Vec<usize>
+    bad_chars: DenseTable,
+    good_suffixes: Vec<usize>,
+    rare_bytes: RareBytes,
 }
 
 impl <'a> BoyerMooreMemchr <'a> {
-    pub fn new(needle: &'a [u8]) -> BoyerMooreMemchr {
-        BoyerMooreMemchr { 
-            needle: needle,
-            bad_chars: build_bad_chars_table(&needle),
-            good_suffixes: build_good_suffixes_table(&needle)
+    /// Construct a new search object, and pre-compute the skip tables and the rare bytes of the
+    /// needle.
+    pub fn new(needle: &'a [u8]) -> BoyerMooreMemchr<'a> {
+        BoyerMooreMemchr {
+            needle,
+            bad_chars: build_bad_chars_table(needle, false),
+            good_suffixes: build_good_suffixes_table(needle),
+            rare_bytes: RareBytes::new(needle),
         }
     }
-}
 
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[u8] {
+        self.needle
+    }
+}
 
-impl <'a> SearchIn<'a, [u8]> for BoyerMooreMemchr<'a> {
-    type Iter = BoyerMooreMemchrIter<'a>;
 
-    fn find_in(&'a self, haystack: &'a [u8]) -> BoyerMooreMemchrIter<'a> {
+impl <'a> SearchIn<[u8]> for BoyerMooreMemchr<'a> {
+    type Iter<'b> = BoyerMooreMemchrIter<'b> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches; the first character of a match 
+    /// will start after the last character of the previous match.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMooreMemchr, SearchIn};
+    /// let needle = BoyerMooreMemchr::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_in<'b>(&'b self, haystack: &'b [u8]) -> BoyerMooreMemchrIter<'b> {
         BoyerMooreMemchrIter {
-            searcher: &self,
-            haystack: haystack,
+            searcher: self,
+            haystack,
             position: 0,
             overlapping_matches: false,
         }
     }
 
-    fn find_overlapping_in(&'a self, haystack: &'a [u8]) -> BoyerMooreMemchrIter<'a> {
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches; the first character of a match is 
+    /// allowed to be matched from within the previous match.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{BoyerMooreMemchr, SearchIn};
+    /// let needle = BoyerMooreMemchr::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [u8]) -> BoyerMooreMemchrIter<'b> {
         BoyerMooreMemchrIter {
-            searcher: &self,
-            haystack: &haystack,
+            searcher: self,
+            haystack,
             position: 0,
             overlapping_matches: true
         }
     }
 }
 
+impl <'a> MatchIn<u8> for BoyerMooreMemchr<'a> {
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
 
 impl <'a> SkipSearch<u8> for &'a BoyerMooreMemchr <'a> {
     #[inline]
-    default fn skip_offset(&self, bad_char: u8, needle_position: usize, haystack: &[u8], haystack_position: usize) -> usize {
-        let skip = max(self.bad_chars[bad_char as usize], self.good_suffixes[needle_position]);
+    fn skip_offset(&self, bad_char: u8, needle_position: usize, haystack: &[u8], haystack_position: usize) -> usize {
+        let skip = max(self.bad_chars.get(bad_char as usize), self.good_suffixes[needle_position]);
         if skip < self.needle.len() {
             skip
         } else {
-            let last_char = self.needle[self.needle.len() - 1];
-            let search_position = haystack_position + 2 * self.needle.len() - 1;
-            memchr(last_char, &haystack[search_position .. ]).map(|x| x + 1).unwrap_or(haystack.len())
+            // the window can move at least a whole needle length, so jump to the next place where
+            // the rare bytes of the needle line up, or past the end if there isn't one
+            self.rare_bytes.find_candidate(haystack, haystack_position + skip, self.needle.len())
+                .map_or(haystack.len(), |candidate| candidate - haystack_position)
         }
     }
 
@@ -62,8 +115,8 @@ impl <'a> SkipSearch<u8> for &'a BoyerMooreMemchr <'a> {
     }
 
     #[inline]
-    fn char_at(&self, index: usize) -> u8 {
-        self.needle[index]
+    fn matches_at(&self, index: usize, c: u8) -> bool {
+        self.needle[index] == c
     }
 }
 
@@ -79,14 +132,9 @@ pub struct BoyerMooreMemchrIter <'a> {
 impl <'a> Iterator for BoyerMooreMemchrIter<'a> {
     type Item = usize;
     fn next(&mut self) -> Option<usize> {
-        find_from_position(&self.searcher, &self.haystack, self.position)
-            .and_then(|position| {
-                if self.overlapping_matches {
-                    self.position = position + 1;
-                } else {
-                    self.position = position + self.searcher.needle.len();
-                }
-                Some(position)
+        find_from_position(&self.searcher, self.haystack, self.position)
+            .inspect(|&position| {
+                self.position = position_after_match(position, self.searcher.needle.len(), self.overlapping_matches);
             })
     }
 }
@@ -154,7 +202,7 @@ pub mod test {
     pub fn test_occurs_in() {
         let needle = BoyerMooreMemchr::new(b"abc");
         let haystack = b"xxxxxxabcxxxxabc";
-        assert_eq!(true, needle.occurs_in(haystack));
+        assert!(needle.occurs_in(haystack));
     }
 
 
@@ -162,7 +210,7 @@ pub mod test {
     pub fn test_not_occurs_in() {
         let needle = BoyerMooreMemchr::new(b"abc");
         let haystack = b"xxxxxxabacxxxxaba";
-        assert_eq!(false, needle.occurs_in(haystack));
+        assert!(!needle.occurs_in(haystack));
     }
 
 
@@ -172,4 +220,44 @@ pub mod test {
         let haystack = b"She sells sea shells on the sea shore.";
         assert_eq!(2, needle.count_in(haystack));
     }
+
+    #[test]
+    pub fn test_empty_needle() {
+        let needle = BoyerMooreMemchr::new(b"");
+        assert_eq!(vec![0,1,2,3], needle.find_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(vec![0,1,2,3], needle.find_overlapping_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(Some(0), needle.find_first_in(b""));
+    }
+
+    #[test]
+    pub fn test_short_haystack() {
+        let needle = BoyerMooreMemchr::new(b"abc");
+        assert_eq!(None, needle.find_first_in(b"ab"));
+        assert_eq!(None, needle.find_first_in(b""));
+    }
+
+    #[test]
+    pub fn test_match_at_end() {
+        // the prefilter must still find a match that ends exactly at the end of the haystack
+        let needle = BoyerMooreMemchr::new(b"Qz!");
+        assert_eq!(Some(0), needle.find_first_in(b"Qz!"));
+        assert_eq!(vec![3, 9], needle.find_in(b"abcQz!defQz!").collect::<Vec<usize>>());
+        assert_eq!(None, needle.find_first_in(b"abcdefghQz"));
+    }
+
+    #[test]
+    pub fn test_periodic_near_end() {
+        // the prefilter used to start its search past the end of the haystack
+        let mut haystack = vec![b'a'; 200];
+        haystack[199] = b'b';
+        let needle = BoyerMooreMemchr::new(&haystack[.. 100]);
+        assert_eq!(100, needle.find_overlapping_in(&haystack).count());
+    }
+
+    #[test]
+    pub fn test_common_last_byte() {
+        let needle = BoyerMooreMemchr::new(b"quiz ");
+        let haystack = b"the quick quiet quiz of the quizzical quiz ";
+        assert_eq!(vec![16, 38], needle.find_in(haystack).collect::<Vec<usize>>());
+    }
 }
@@ -0,0 +1,364 @@
+//! Search for a needle in which each position accepts a set of symbols, like `[0-9]` or `[ACGT]`.
+//! Each position is compiled to a 256-bit set, so only symbols below 256 can be matched. The
+//! search is Boyer-Moore-Horspool, where the skip for a bad character is the distance to the last
+//! position before the end of the needle whose class contains it.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{ClassNeedle, SearchIn};
+//! let needle = ClassNeedle::from_pattern("v[0-9].[0-9]").unwrap();
+//! let haystack = b"released in v2.7 after v2.6";
+//! assert_eq!(vec![12, 23], needle.find_in(haystack).collect::<Vec<usize>>());
+//! ```
+use bad_chars::{BadCharsTable, DenseTable};
+use error::NeedleError;
+use skip_search::*;
+use super::{SearchIn, MatchIn};
+
+/// A set of symbols below 256, which are the symbols accepted at one position of a `ClassNeedle`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct SymbolClass {
+    bits: [u64; 4],
+}
+
+impl SymbolClass {
+    /// A class which accepts no symbols.
+    pub fn new() -> SymbolClass {
+        SymbolClass { bits: [0; 4] }
+    }
+
+    /// A class which accepts every symbol below 256.
+    pub fn any() -> SymbolClass {
+        SymbolClass { bits: [!0; 4] }
+    }
+
+    /// A class which accepts each of the given symbols.
+    pub fn from_bytes(symbols: &[u8]) -> SymbolClass {
+        let mut class = SymbolClass::new();
+        for &c in symbols {
+            class.insert(c);
+        }
+        class
+    }
+
+    /// Add `c` to the class.
+    pub fn insert(&mut self, c: u8) {
+        self.bits[c as usize / 64] |= 1 << (c % 64);
+    }
+
+    /// Add every symbol from `first` to `last`, inclusive, to the class.
+    pub fn insert_range(&mut self, first: u8, last: u8) {
+        for c in first ..= last {
+            self.insert(c);
+        }
+    }
+
+    /// Whether the class accepts `c`. Symbols of 256 or more are never accepted.
+    #[inline]
+    pub fn contains(&self, c: usize) -> bool {
+        c < 256 && self.bits[c / 64] & (1 << (c % 64)) != 0
+    }
+}
+
+
+pub struct ClassNeedle {
+    classes: Vec<SymbolClass>,
+    bad_chars: DenseTable,
+}
+
+impl ClassNeedle {
+    /// Construct a new search object from the class of each position, and pre-compute the skip
+    /// table.
+    pub fn new(classes: Vec<SymbolClass>) -> ClassNeedle {
+        let len = classes.len();
+        let mut bad_chars = DenseTable::new(len);
+        for (i, class) in classes.iter().enumerate().take(len.saturating_sub(1)) {
+            for c in 0 .. 256 {
+                if class.contains(c) {
+                    bad_chars.set(c, len - i - 1);
+                }
+            }
+        }
+        ClassNeedle { classes, bad_chars }
+    }
+
+    /// Construct a new search object from a pattern, in which `[...]` is a class of symbols and
+    /// ranges such as `[a-z0-9_]`, `.` accepts any symbol, `\` escapes the next character, and
+    /// any other character is matched exactly. The pattern is read as bytes, so a non-ASCII
+    /// character is matched as its UTF-8 bytes, and can't be used within a class. Returns an
+    /// error if the pattern is empty or ends with an unescaped `\`, or if a class is empty, isn't
+    /// closed, or has a range that runs backwards.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{ClassNeedle, NeedleError};
+    /// assert!(ClassNeedle::from_pattern(r"[A-Fa-f0-9][A-Fa-f0-9]\.").is_ok());
+    /// assert_eq!(Some(NeedleError::InvalidSignature(2)), ClassNeedle::from_pattern("ab[cd").err());
+    /// ```
+    pub fn from_pattern(pattern: &str) -> Result<ClassNeedle, NeedleError> {
+        let bytes = pattern.as_bytes();
+        let mut classes = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let class = match bytes[i] {
+                b'.' => SymbolClass::any(),
+                b'[' => {
+                    let start = i;
+                    let mut class = SymbolClass::new();
+                    i += 1;
+                    let mut empty = true;
+                    loop {
+                        let first = match bytes.get(i) {
+                            None => return Err(NeedleError::InvalidSignature(start)),
+                            Some(&b']') => break,
+                            _ => class_byte(bytes, &mut i, start)?,
+                        };
+                        match (bytes.get(i), bytes.get(i + 1)) {
+                            (Some(&b'-'), Some(&end)) if end != b']' => {
+                                i += 1;
+                                let last = class_byte(bytes, &mut i, start)?;
+                                if first > last {
+                                    return Err(NeedleError::InvalidSignature(start));
+                                }
+                                class.insert_range(first, last);
+                            }
+                            _ => class.insert(first),
+                        }
+                        empty = false;
+                    }
+                    if empty {
+                        return Err(NeedleError::InvalidSignature(start));
+                    }
+                    class
+                }
+                b'\\' => {
+                    let start = i;
+                    i += 1;
+                    let c = *bytes.get(i).ok_or(NeedleError::InvalidSignature(start))?;
+                    SymbolClass::from_bytes(&[c])
+                }
+                c => SymbolClass::from_bytes(&[c]),
+            };
+            classes.push(class);
+            i += 1;
+        }
+        if classes.is_empty() {
+            return Err(NeedleError::EmptyNeedle);
+        }
+        Ok(ClassNeedle::new(classes))
+    }
+
+    /// Construct a new search object from a DNA or RNA sequence written with IUPAC nucleotide
+    /// codes, so that `N` accepts any base, `R` accepts `A` or `G`, `Y` accepts `C` or `T`, and
+    /// so on. Codes can be upper or lower case, and each base is matched in either case. Returns
+    /// an error if the sequence is empty or contains a character which isn't a code.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{ClassNeedle, SearchIn};
+    /// let needle = ClassNeedle::from_iupac(b"GANTC").unwrap();
+    /// assert_eq!(Some(3), needle.find_first_in(b"TTCGATTCAA"));
+    /// ```
+    pub fn from_iupac(sequence: &[u8]) -> Result<ClassNeedle, NeedleError> {
+        if sequence.is_empty() {
+            return Err(NeedleError::EmptyNeedle);
+        }
+        let classes = sequence.iter().enumerate().map(|(i, &code)| {
+            let bases: &[u8] = match code.to_ascii_uppercase() {
+                b'A' => b"A",
+                b'C' => b"C",
+                b'G' => b"G",
+                b'T' => b"T",
+                b'U' => b"U",
+                b'R' => b"AG",
+                b'Y' => b"CTU",
+                b'S' => b"CG",
+                b'W' => b"ATU",
+                b'K' => b"GTU",
+                b'M' => b"AC",
+                b'B' => b"CGTU",
+                b'D' => b"AGTU",
+                b'H' => b"ACTU",
+                b'V' => b"ACG",
+                b'N' => b"ACGTU",
+                _ => return Err(NeedleError::InvalidSignature(i)),
+            };
+            let mut class = SymbolClass::new();
+            for &base in bases {
+                class.insert(base);
+                class.insert(base.to_ascii_lowercase());
+            }
+            Ok(class)
+        }).collect::<Result<Vec<_>, _>>()?;
+        Ok(ClassNeedle::new(classes))
+    }
+
+    /// The classes of the symbols accepted at each position of the needle.
+    pub fn classes(&self) -> &[SymbolClass] {
+        &self.classes
+    }
+}
+
+
+// Read one byte of a bracketed class at `i`, following a `\` escape, and move `i` past it. An
+// escape at the end of the pattern is an error in the class that starts at `start`.
+fn class_byte(bytes: &[u8], i: &mut usize, start: usize) -> Result<u8, NeedleError> {
+    if bytes[*i] == b'\\' {
+        *i += 1;
+    }
+    let c = *bytes.get(*i).ok_or(NeedleError::InvalidSignature(start))?;
+    *i += 1;
+    Ok(c)
+}
+
+impl <T> SearchIn<[T]> for ClassNeedle
+    where T: Copy + PartialEq + Into<usize>
+{
+    type Iter<'b> = ClassNeedleIter<'b, T> where T: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches.
+    fn find_in<'b>(&'b self, haystack: &'b [T]) -> ClassNeedleIter<'b, T> {
+        ClassNeedleIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            overlapping_matches: false,
+        }
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{ClassNeedle, SearchIn};
+    /// let needle = ClassNeedle::from_pattern("[ab][ab]").unwrap();
+    /// assert_eq!(vec![0, 1, 4], needle.find_overlapping_in(b"abbxab").collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> ClassNeedleIter<'b, T> {
+        ClassNeedleIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            overlapping_matches: true,
+        }
+    }
+}
+
+impl <T> MatchIn<T> for ClassNeedle
+    where T: Copy + PartialEq + Into<usize>
+{
+    fn match_len(&self) -> usize {
+        self.classes.len()
+    }
+}
+
+impl <T> SkipSearch<T> for &ClassNeedle
+    where T: Copy + Into<usize>
+{
+    #[inline]
+    fn skip_offset(&self, bad_char: T, _: usize, _haystack: &[T], _haystack_position: usize) -> usize {
+        self.bad_chars.get(bad_char.into())
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    #[inline]
+    fn matches_at(&self, index: usize, c: T) -> bool {
+        self.classes[index].contains(c.into())
+    }
+}
+
+
+pub struct ClassNeedleIter <'a, T: 'a> {
+    searcher: &'a ClassNeedle,
+    haystack: &'a [T],
+    position: usize,
+    overlapping_matches: bool,
+}
+
+impl <'a, T> Iterator for ClassNeedleIter<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        find_from_position(&self.searcher, self.haystack, self.position)
+            .inspect(|&position| {
+                self.position = position_after_match(position, self.searcher.classes.len(), self.overlapping_matches);
+            })
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+    use test_util::{Random, naive_find};
+
+    #[test]
+    pub fn test_symbol_class() {
+        let mut class = SymbolClass::from_bytes(b"ax");
+        class.insert_range(b'0', b'9');
+        assert!(class.contains(b'a' as usize) && class.contains(b'5' as usize) && class.contains(b'x' as usize));
+        assert!(!class.contains(b'b' as usize));
+        assert!(!SymbolClass::any().contains(256));
+        assert!(SymbolClass::any().contains(255));
+    }
+
+    #[test]
+    pub fn test_from_pattern() {
+        let needle = ClassNeedle::from_pattern(r"[0-9][0-9]:[0-5][0-9]").unwrap();
+        assert_eq!(vec![5, 12], needle.find_in(b"from 09:30 (10:15)").collect::<Vec<usize>>());
+        let needle = ClassNeedle::from_pattern(r"a\.[-x\]]").unwrap();
+        assert_eq!(vec![0, 4, 8], needle.find_in(b"a.-.a.x.a.]a.b").collect::<Vec<usize>>());
+        assert_eq!(Some(NeedleError::EmptyNeedle), ClassNeedle::from_pattern("").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(1)), ClassNeedle::from_pattern(r"a[\").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(1)), ClassNeedle::from_pattern("a[z-a]").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(2)), ClassNeedle::from_pattern("a.[]").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(0)), ClassNeedle::from_pattern(r"[a-\").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(2)), ClassNeedle::from_pattern(r"ab\").err());
+        assert_eq!(Some(2), ClassNeedle::from_pattern(r"b\\").unwrap().find_first_in(br"a\b\"));
+        // the end of a range can be escaped, like any other byte in a class
+        let needle = ClassNeedle::from_pattern(r"[Z-\]]").unwrap();
+        assert!(needle.classes()[0].contains(b'\\' as usize));
+        assert!(needle.classes()[0].contains(b']' as usize));
+        assert!(!needle.classes()[0].contains(b'a' as usize));
+    }
+
+    #[test]
+    pub fn test_iupac() {
+        let needle = ClassNeedle::from_iupac(b"RGATCY").unwrap();
+        let haystack = b"AGATCTTTGGATCCaaagatcc";
+        assert_eq!(vec![0, 8, 16], needle.find_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(3, needle.count_in(haystack));
+        assert_eq!(Some(NeedleError::InvalidSignature(2)), ClassNeedle::from_iupac(b"AC-GT").err());
+    }
+
+    #[test]
+    pub fn test_large_symbols() {
+        let needle = ClassNeedle::from_pattern("[ab]c").unwrap();
+        let haystack: Vec<u16> = vec![0x161, b'c' as u16, b'b' as u16, b'c' as u16];
+        assert_eq!(Some(2), needle.find_first_in(&haystack));
+    }
+
+    #[test]
+    pub fn test_agrees_with_naive() {
+        let mut random = Random::new(11);
+        let haystack: Vec<u8> = (0 .. 2000).map(|_| random.below(6) as u8).collect();
+        for _ in 0 .. 200 {
+            let len = 1 + random.below(8) as usize;
+            let classes: Vec<SymbolClass> = (0 .. len)
+                .map(|_| SymbolClass::from_bytes(&[random.below(6) as u8, random.below(6) as u8]))
+                .collect();
+            let expected = naive_find(&haystack, len, |window| window.iter().zip(&classes).all(|(&c, class)| class.contains(c as usize)));
+            let searcher = ClassNeedle::new(classes);
+            assert_eq!(expected, searcher.find_overlapping_in(&haystack).collect::<Vec<usize>>());
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Errors returned by the fallible constructors of the search objects. The infallible constructors
+//! accept any needle, and panic if the needle can't be searched for.
+use std::error::Error;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NeedleError {
+    /// The needle is empty. The infallible constructors allow this, and the needle then matches at
+    /// every position of the haystack, but it is usually a mistake.
+    EmptyNeedle,
+    /// The needle contains a symbol that the bad character table can't store, such as a symbol of
+    /// 256 or more in a `DenseTable`. The value is the symbol converted `Into<usize>`.
+    UnsupportedSymbol(usize),
+    /// A textual needle, such as a hex signature or a class pattern, could not be parsed. The value
+    /// is the byte offset of the token which is invalid.
+    InvalidSignature(usize),
+    /// The needle is longer than the search algorithm supports, such as more than 64 symbols for
+    /// `ShiftOr`. The value is the length of the needle.
+    TooLong(usize),
+}
+
+impl fmt::Display for NeedleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NeedleError::EmptyNeedle => write!(f, "the needle is empty"),
+            NeedleError::UnsupportedSymbol(c) => write!(f, "the symbol {} is not supported by the bad character table", c),
+            NeedleError::InvalidSignature(offset) => write!(f, "the needle has an invalid token at offset {}", offset),
+            NeedleError::TooLong(len) => write!(f, "the needle of length {} is too long for this searcher", len),
+        }
+    }
+}
+
+impl Error for NeedleError {}
@@ -11,12 +11,24 @@
 //! let haystack = b"This is an example of searching for a word";
 //! assert_eq!(Some(11), needle.find_in(haystack).next());
 //! ```
+use std::borrow::Cow;
+use bad_chars::{BadCharsTable, AutoTable};
+use error::NeedleError;
 use skip_search::*;
-use super::SearchIn;
+use std::io::Read;
+use iter_search::SkipSearchIter;
+use read_search::ReadSearchIter;
+use str_search::StrSearch;
+use std::iter::Rev;
+use std::ops::Range;
+use search_state::SearchState;
+use super::{SearchIn, MatchIn, ReverseSearchIn, SearchInIter, SearchInRead};
 
-pub struct Horspool <'a, T:'a> {
-    needle: &'a [T],
-    bad_chars: [usize; 256],
+pub struct Horspool <'a, T:'a + Clone, B = AutoTable> {
+    needle: Cow<'a, [T]>,
+    bad_chars: B,
+    rev_bad_chars: B,
+    ascii_case_insensitive: bool,
 }
 
 
@@ -26,19 +38,247 @@ impl <'a, T> Horspool <'a, T>
     /// Construct a new Horspool search object, and pre-compute the skip tables.
     /// If you intend to search for the same needle in multiple haystacks, it is more
     /// efficient to create just one instance and then re-use it."
-    pub fn new(needle: &'a [T]) -> Horspool<T> {
+    pub fn new(needle: &'a [T]) -> Horspool<'a, T> {
+        Horspool::from_cow(Cow::Borrowed(needle), false)
+    }
+
+    /// Construct a new Horspool search object which treats ASCII upper and lower case letters as
+    /// equal, both in the needle and in the haystack. Other symbols must match exactly.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchIn};
+    /// let needle = Horspool::new_ascii_case_insensitive(b"content-length:");
+    /// let haystack = b"Host: example.com\r\nContent-Length: 42\r\n";
+    /// assert_eq!(Some(19), needle.find_first_in(haystack));
+    /// ```
+    pub fn new_ascii_case_insensitive(needle: &'a [T]) -> Horspool<'a, T> {
+        Horspool::from_cow(Cow::Borrowed(needle), true)
+    }
+
+    /// Construct a new Horspool search object, or return an error if the needle is empty.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, NeedleError};
+    /// assert!(Horspool::try_new(b"abc").is_ok());
+    /// assert_eq!(Some(NeedleError::EmptyNeedle), Horspool::try_new(b"").err());
+    /// ```
+    pub fn try_new(needle: &'a [T]) -> Result<Horspool<'a, T>, NeedleError> {
+        Horspool::try_with_table(needle)
+    }
+}
+
+impl <'a> Horspool <'a, u8> {
+    /// Construct a new Horspool search object for searching in `str`s. The offsets that it finds
+    /// are always on char boundaries, so they can be used to index into the haystack.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchIn};
+    /// let needle = Horspool::new_str("ñu");
+    /// let haystack = "El ñu come";
+    /// let position = needle.find_first_in(haystack).unwrap();
+    /// assert_eq!("ñu come", &haystack[position ..]);
+    /// ```
+    pub fn new_str(needle: &'a str) -> StrSearch<Horspool<'a, u8>> {
+        StrSearch::new(Horspool::new(needle.as_bytes()), needle.len())
+    }
+}
+impl <'a, T, B> Horspool <'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    /// Construct a new Horspool search object, which uses a specific type of bad character table
+    /// rather than the default `AutoTable`. For example, a `HashedTable` uses much less memory
+    /// than the other tables when the needle is short and the alphabet is large.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, HashedTable, SearchIn};
+    /// let code_units: Vec<u16> = "ŝearch ĥere".encode_utf16().collect();
+    /// let needle: Horspool<u16, HashedTable> = Horspool::with_table(&code_units[7 ..]);
+    /// assert_eq!(Some(7), needle.find_first_in(&code_units));
+    /// ```
+    pub fn with_table(needle: &'a [T]) -> Horspool<'a, T, B> {
+        Horspool::from_cow(Cow::Borrowed(needle), false)
+    }
+
+    /// Construct a new Horspool search object with a specific type of bad character table, or return
+    /// an error if the needle is empty or contains a symbol that the table can't store.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, DenseTable, NeedleError};
+    /// let needle: Result<Horspool<u16, DenseTable>, _> = Horspool::try_with_table(&[1, 2, 300]);
+    /// assert_eq!(Some(NeedleError::UnsupportedSymbol(300)), needle.err());
+    /// ```
+    pub fn try_with_table(needle: &'a [T]) -> Result<Horspool<'a, T, B>, NeedleError> {
+        check_needle::<T, B>(needle)?;
+        Ok(Horspool::with_table(needle))
+    }
+
+    fn from_cow(needle: Cow<'a, [T]>, ascii_case_insensitive: bool) -> Horspool<'a, T, B> {
         Horspool { 
-            needle: needle,
-            bad_chars: build_bad_chars_table(&needle),
+            bad_chars: build_bad_chars_table(&needle, ascii_case_insensitive),
+            rev_bad_chars: build_rev_bad_chars_table(&needle, ascii_case_insensitive),
+            needle,
+            ascii_case_insensitive,
         }
     }
-}
 
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[T] {
+        &self.needle
+    }
 
-impl <'a, T> SearchIn<'a, [T]> for Horspool<'a, T>
+    /// Whether ASCII upper and lower case letters are treated as equal.
+    pub fn is_ascii_case_insensitive(&self) -> bool {
+        self.ascii_case_insensitive
+    }
+
+    /// Convert into a search object that owns its needle, copying the needle if it was borrowed.
+    /// The skip tables are moved across rather than being computed again.
+    pub fn into_owned(self) -> Horspool<'static, T, B> {
+        Horspool {
+            needle: Cow::Owned(self.needle.into_owned()),
+            ..self
+        }
+    }
+
+    /// Returns an iterator that will produce the indices of the needle within `range` of the
+    /// haystack. Only matches which lie entirely within the range are found, and the indices are
+    /// offsets into the whole haystack. This iterator will not find overlapping matches.
+    ///
+    /// # Panics
+    /// If the range is not within the haystack.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::Horspool;
+    /// let needle = Horspool::new(b"ab");
+    /// let haystack = b"ab ab ab ab";
+    /// assert_eq!(vec![3, 6], needle.find_in_range(haystack, 2 .. 10).collect::<Vec<usize>>());
+    /// ```
+    pub fn find_in_range<'b>(&'b self, haystack: &'b [T], range: Range<usize>) -> HorspoolIter<'b, T, B> {
+        check_range(&range, haystack.len());
+        self.iter(haystack, range.start, range.end, false)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle within `range` of the
+    /// haystack, as offsets into the whole haystack. This iterator will find overlapping matches.
+    ///
+    /// # Panics
+    /// If the range is not within the haystack.
+    pub fn find_overlapping_in_range<'b>(&'b self, haystack: &'b [T], range: Range<usize>) -> HorspoolIter<'b, T, B> {
+        check_range(&range, haystack.len());
+        self.iter(haystack, range.start, range.end, true)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// at `start`. This iterator will not find overlapping matches.
+    ///
+    /// # Panics
+    /// If `start` is past the end of the haystack.
+    pub fn find_from<'b>(&'b self, haystack: &'b [T], start: usize) -> HorspoolIter<'b, T, B> {
+        check_range(&(start .. haystack.len()), haystack.len());
+        self.iter(haystack, start, haystack.len(), false)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// at `start`. This iterator will find overlapping matches.
+    ///
+    /// # Panics
+    /// If `start` is past the end of the haystack.
+    pub fn find_overlapping_from<'b>(&'b self, haystack: &'b [T], start: usize) -> HorspoolIter<'b, T, B> {
+        check_range(&(start .. haystack.len()), haystack.len());
+        self.iter(haystack, start, haystack.len(), true)
+    }
+
+    /// Carry on a search from a `SearchState`, which was saved from an iterator of this searcher
+    /// over the same haystack. The search carries on from the saved position up to the saved end,
+    /// finding overlapping matches only if the saved iterator did. Horspool doesn't remember any
+    /// part of the needle between matches, so only these offsets are carried over. A state is only
+    /// valid for the same needle: with any other, the search won't carry on from where it stopped.
+    ///
+    /// # Panics
+    /// If the state refers to offsets past the end of the haystack.
+    pub fn resume_in<'b>(&'b self, haystack: &'b [T], state: SearchState) -> HorspoolIter<'b, T, B> {
+        // only the end is checked, as a finished search may have moved its position past the end
+        assert!(state.end <= haystack.len(), "the search state is for a longer haystack");
+        self.iter(haystack, state.position, state.end, state.overlapping_matches)
+    }
+
+    fn iter<'b>(&'b self, haystack: &'b [T], position: usize, end: usize, overlapping_matches: bool) -> HorspoolIter<'b, T, B> {
+        HorspoolIter {
+            searcher: self,
+            haystack,
+            position,
+            end,
+            overlapping_matches,
+        }
+    }
+}
+
+impl <T> Horspool <'static, T>
     where T: Copy + PartialEq + Into<usize>
 {
-    type Iter = HorspoolIter<'a, T>;
+    /// Construct a new Horspool search object which owns its needle. Unlike one created with
+    /// `new`, it does not borrow anything, so it can be kept in a long-lived struct or a static
+    /// and used to search in haystacks of any lifetime.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchIn};
+    /// struct Config { separator: Horspool<'static, u8> }
+    /// let config = Config { separator: Horspool::from_vec(b"\r\n\r\n".to_vec()) };
+    /// let buffer = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+    /// assert_eq!(Some(33), config.separator.find_first_in(&buffer));
+    /// ```
+    pub fn from_vec(needle: Vec<T>) -> Horspool<'static, T> {
+        Horspool::from_cow(Cow::Owned(needle), false)
+    }
+
+    /// Construct a new Horspool search object which owns its needle, and treats ASCII upper and
+    /// lower case letters as equal, like one created with `new_ascii_case_insensitive`.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchIn};
+    /// let header = Horspool::from_vec_ascii_case_insensitive(b"content-length:".to_vec());
+    /// let haystack = b"Host: example.com\r\nContent-Length: 42\r\n".to_vec();
+    /// assert_eq!(Some(19), header.find_first_in(&haystack));
+    /// ```
+    pub fn from_vec_ascii_case_insensitive(needle: Vec<T>) -> Horspool<'static, T> {
+        Horspool::from_cow(Cow::Owned(needle), true)
+    }
+}
+
+impl <T, B> Horspool <'static, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    /// Construct a new Horspool search object which owns its needle, and uses a specific type of
+    /// bad character table, like one created with `with_table`.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, HashedTable, SearchIn};
+    /// let code_units: Vec<u16> = "ŝearch ĥere".encode_utf16().collect();
+    /// let needle: Horspool<u16, HashedTable> = Horspool::from_vec_with_table(code_units[7 ..].to_vec());
+    /// assert_eq!(Some(7), needle.find_first_in(&code_units));
+    /// ```
+    pub fn from_vec_with_table(needle: Vec<T>) -> Horspool<'static, T, B> {
+        Horspool::from_cow(Cow::Owned(needle), false)
+    }
+}
+
+
+impl <'a, T, B> SearchIn<[T]> for Horspool<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    type Iter<'b> = HorspoolIter<'b, T, B> where Self: 'b;
 
     /// Returns an iterator that will produce the indices of the needle in the haystack.
     /// This iterator will not find overlapping matches; the first character of a match 
@@ -51,13 +291,8 @@ impl <'a, T> SearchIn<'a, [T]> for Horspool<'a, T>
     /// let haystack = b"aabaabaabaabaaba";
     /// assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
     /// ```
-    fn find_in(&'a self, haystack: &'a [T]) -> HorspoolIter<'a, T> {
-        HorspoolIter {
-            searcher: &self,
-            haystack: haystack,
-            position: 0,
-            overlapping_matches: false,
-        }
+    fn find_in<'b>(&'b self, haystack: &'b [T]) -> HorspoolIter<'b, T, B> {
+        self.iter(haystack, 0, haystack.len(), false)
     }
 
     /// Returns an iterator that will produce the indices of the needle in the haystack.
@@ -71,23 +306,140 @@ impl <'a, T> SearchIn<'a, [T]> for Horspool<'a, T>
     /// let haystack = b"aabaabaabaabaaba";
     /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
     /// ```
-    fn find_overlapping_in(&'a self, haystack: &'a [T]) -> HorspoolIter<'a, T> {
-        HorspoolIter {
-            searcher: &self,
-            haystack: &haystack,
-            position: 0,
-            overlapping_matches: true
-        }
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> HorspoolIter<'b, T, B> {
+        self.iter(haystack, 0, haystack.len(), true)
+    }
+}
+
+impl <'a, T, B> MatchIn<T> for Horspool<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    fn match_len(&self) -> usize {
+        self.needle.len()
     }
 }
 
 
-impl <'a, T> SkipSearch<T> for &'a Horspool <'a, T>
-    where T: Copy + Into<usize>
+impl <'a, T, B> ReverseSearchIn<[T]> for Horspool<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    type RevIter<'b> = Rev<HorspoolIter<'b, T, B>> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// from the end. This iterator will not find overlapping matches; the last character of a match
+    /// will come before the first character of the previously found match. Note that if the needle
+    /// can overlap with itself, these may not be the same matches that `find_in` finds.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, ReverseSearchIn};
+    /// let needle = Horspool::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![12,6,0], needle.rfind_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn rfind_in<'b>(&'b self, haystack: &'b [T]) -> Rev<HorspoolIter<'b, T, B>> {
+        self.find_in(haystack).rev()
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack, starting
+    /// from the end. This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, ReverseSearchIn};
+    /// let needle = Horspool::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![12,9,6,3,0], needle.rfind_overlapping_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn rfind_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> Rev<HorspoolIter<'b, T, B>> {
+        self.find_overlapping_in(haystack).rev()
+    }
+}
+
+impl <'a, T, B, I> SearchInIter<I> for Horspool<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable,
+          I: IntoIterator<Item = T>
+{
+    type Iter<'b> = SkipSearchIter<&'b Horspool<'b, T, B>, I::IntoIter> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the elements of
+    /// `haystack`, consuming it as it goes. This iterator will not find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchInIter};
+    /// let needle = Horspool::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba".iter().cloned();
+    /// assert_eq!(vec![0,6,12], needle.find_in_iter(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_in_iter(&self, haystack: I) -> Self::Iter<'_> {
+        SkipSearchIter::new(self, haystack.into_iter(), false)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the elements of
+    /// `haystack`, consuming it as it goes. This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchInIter};
+    /// let needle = Horspool::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba".iter().cloned();
+    /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in_iter(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in_iter(&self, haystack: I) -> Self::Iter<'_> {
+        SkipSearchIter::new(self, haystack.into_iter(), true)
+    }
+}
+
+
+impl <'a, B, R> SearchInRead<R> for Horspool<'a, u8, B>
+    where B: BadCharsTable,
+          R: Read
+{
+    type Iter<'b> = ReadSearchIter<&'b Horspool<'b, u8, B>, R> where Self: 'b;
+
+    /// Returns an iterator that will produce the byte offsets of the needle in the stream read
+    /// from `reader`. This iterator will not find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchInRead};
+    /// let needle = Horspool::new(b"aaba");
+    /// let reader = &b"aabaabaabaabaaba"[..];
+    /// let found: Vec<usize> = needle.find_in_read(reader).map(Result::unwrap).collect();
+    /// assert_eq!(vec![0,6,12], found);
+    /// ```
+    fn find_in_read(&self, reader: R) -> Self::Iter<'_> {
+        ReadSearchIter::new(self, reader, false)
+    }
+
+    /// Returns an iterator that will produce the byte offsets of the needle in the stream read
+    /// from `reader`. This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Horspool, SearchInRead};
+    /// let needle = Horspool::new(b"aaba");
+    /// let reader = &b"aabaabaabaabaaba"[..];
+    /// let found: Vec<usize> = needle.find_overlapping_in_read(reader).map(Result::unwrap).collect();
+    /// assert_eq!(vec![0,3,6,9,12], found);
+    /// ```
+    fn find_overlapping_in_read(&self, reader: R) -> Self::Iter<'_> {
+        ReadSearchIter::new(self, reader, true)
+    }
+}
+
+
+impl <'a, T, B> SkipSearch<T> for &'a Horspool <'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
 {
     #[inline]
     fn skip_offset(&self, bad_char: T, _: usize, _haystack: &[T], _haystack_position: usize) -> usize {
-        self.bad_chars[bad_char.into()]
+        self.bad_chars.get(bad_char.into())
     }
 
     #[inline]
@@ -96,32 +448,85 @@ impl <'a, T> SkipSearch<T> for &'a Horspool <'a, T>
     }
 
     #[inline]
-    fn char_at(&self, index: usize) -> T {
-        self.needle[index]
+    fn matches_at(&self, index: usize, c: T) -> bool {
+        symbols_match(self.needle[index], c, self.ascii_case_insensitive)
     }
 }
 
-pub struct HorspoolIter <'a, T:'a> {
-    searcher: &'a Horspool<'a, T>,
+
+impl <'a, T, B> ReverseSkipSearch<T> for &'a Horspool <'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    #[inline]
+    fn rev_skip_offset(&self, bad_char: T, _: usize, _haystack: &[T], _haystack_position: usize) -> usize {
+        self.rev_bad_chars.get(bad_char.into())
+    }
+}
+
+pub struct HorspoolIter <'a, T:'a + Clone, B:'a = AutoTable> {
+    searcher: &'a Horspool<'a, T, B>,
     haystack: &'a [T],
     position: usize,
+    end: usize,
     overlapping_matches: bool,
 }
 
 
-impl <'a, T> Iterator for HorspoolIter<'a, T> 
-    where T: Copy + PartialEq + Into<usize>
+impl <'a, T: 'a + Clone, B> HorspoolIter<'a, T, B> {
+    /// Save the progress of the search, so that it can be carried on later with `resume_in`.
+    pub fn state(&self) -> SearchState {
+        SearchState {
+            position: self.position,
+            end: self.end,
+            memory: 0,
+            rev_memory: 0,
+            overlapping_matches: self.overlapping_matches,
+        }
+    }
+}
+
+impl <'a, T, B> Iterator for HorspoolIter<'a, T, B> 
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
 {
     type Item = usize;
     fn next(&mut self) -> Option<usize> {
-        find_from_position(&self.searcher, &self.haystack, self.position)
-            .and_then(|position| {
-                if self.overlapping_matches {
-                    self.position = position + 1;
+        if self.end < self.searcher.needle.len() {
+            return None;
+        }
+        find_from_position(&self.searcher, &self.haystack[.. self.end], self.position)
+            .inspect(|&position| {
+                self.position = position_after_match(position, self.searcher.needle.len(), self.overlapping_matches);
+            })
+    }
+}
+
+impl <'a, T, B> DoubleEndedIterator for HorspoolIter<'a, T, B> 
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    fn next_back(&mut self) -> Option<usize> {
+        if self.position > self.end {
+            return None;
+        }
+        let start = self.position;
+        rfind_from_position(&self.searcher, &self.haystack[start ..], self.end - start)
+            .map(|position| position + start)
+            .inspect(|&position| {
+                let len = self.searcher.needle.len();
+                if len == 0 {
+                    // an empty needle matches at every position, so move back past this one
+                    if position == 0 {
+                        self.position = 1;
+                    } else {
+                        self.end = position - 1;
+                    }
+                } else if self.overlapping_matches {
+                    self.end = position + len - 1;
                 } else {
-                    self.position = position + self.searcher.needle.len();
+                    self.end = position;
                 }
-                Some(position)
             })
     }
 }
@@ -0,0 +1,131 @@
+//! Searching in the elements of an iterator. Only a needle-sized window of the haystack is
+//! kept in memory, so the haystack never needs to be collected up front.
+use std::collections::VecDeque;
+use std::iter::Fuse;
+use skip_search::{SkipSearch, position_after_match};
+
+pub struct SkipSearchIter <N, I: Iterator> {
+    needle: N,
+    haystack: Fuse<I>,
+    window: VecDeque<I::Item>,
+    position: usize,
+    overlapping_matches: bool,
+    // set once the haystack is used up, so that an empty needle stops matching after its end
+    finished: bool,
+}
+
+impl <N, I> SkipSearchIter<N, I>
+    where I: Iterator,
+          I::Item: Copy + PartialEq,
+          N: SkipSearch<I::Item>
+{
+    pub fn new(needle: N, haystack: I, overlapping_matches: bool) -> SkipSearchIter<N, I> {
+        let window = VecDeque::with_capacity(needle.len());
+        SkipSearchIter {
+            needle,
+            haystack: haystack.fuse(),
+            window,
+            position: 0,
+            overlapping_matches,
+            finished: false,
+        }
+    }
+
+    // Slide the window forward, pulling (and discarding) elements from the haystack if the
+    // skip goes beyond the elements that are currently buffered.
+    fn advance(&mut self, skip: usize) {
+        self.position += skip;
+        if skip <= self.window.len() {
+            self.window.drain(.. skip);
+        } else {
+            let remaining = skip - self.window.len();
+            self.window.clear();
+            self.haystack.nth(remaining - 1);
+        }
+    }
+}
+
+impl <N, I> Iterator for SkipSearchIter<N, I>
+    where I: Iterator,
+          I::Item: Copy + PartialEq,
+          N: SkipSearch<I::Item>
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        let len = self.needle.len();
+        if len == 0 {
+            if self.finished {
+                return None;
+            }
+            let position = self.position;
+            if self.haystack.next().is_some() {
+                self.position += 1;
+            } else {
+                self.finished = true;
+            }
+            return Some(position);
+        }
+        loop {
+            while self.window.len() < len {
+                let c = self.haystack.next()?;
+                self.window.push_back(c);
+            }
+            let window = self.window.make_contiguous();
+            let mut needle_position = len - 1;
+            while self.needle.matches_at(needle_position, window[needle_position]) {
+                if needle_position == 0 {
+                    let position = self.position;
+                    self.advance(position_after_match(0, len, self.overlapping_matches));
+                    return Some(position);
+                } else {
+                    needle_position -= 1;
+                }
+            }
+            let bad_char = window[len - 1];
+            let skip = self.needle.skip_offset(bad_char, needle_position, window, 0);
+            self.advance(skip);
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::super::{BoyerMoore, Horspool, SearchInIter};
+
+    #[test]
+    pub fn test_find_in_iter() {
+        let needle = BoyerMoore::new(b"xyz");
+        let haystack = b"01xyzxyz890xyz45xyz".iter().cloned();
+        assert_eq!(vec![2,5,11,16], needle.find_in_iter(haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_overlapping() {
+        let needle = Horspool::new(b"aaba");
+        let haystack = b"aabaabaabaabaaba".iter().cloned();
+        assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in_iter(haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_non_overlapping() {
+        let needle = BoyerMoore::new(b"aaba");
+        let haystack = b"aabaabaabaabaaba".iter().cloned();
+        assert_eq!(vec![0,6,12], needle.find_in_iter(haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_generated_haystack() {
+        let needle = Horspool::new(&[7u8, 8, 9]);
+        let haystack = (0 .. 1000).map(|i| (i % 10) as u8);
+        assert_eq!((0 .. 100).map(|i| i * 10 + 7).collect::<Vec<usize>>(),
+            needle.find_in_iter(haystack).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_skip_past_window() {
+        let needle = BoyerMoore::new(b"needle");
+        let haystack = b"haystack haystack haystack needle hay".iter().cloned();
+        assert_eq!(Some(27), needle.find_in_iter(haystack).next());
+    }
+}
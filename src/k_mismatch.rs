@@ -0,0 +1,318 @@
+//! Approximate search, for every position where the needle matches with at most `k` mismatched
+//! symbols (a Hamming distance of at most `k`).
+//!
+//! The needle is split into `k + 1` pieces. Any approximate match has at most `k` mismatches, so
+//! at least one of the pieces must match exactly, and the pieces are found with `Horspool`. Only
+//! the positions that they suggest are then compared with the whole needle. When `k` is small
+//! relative to the length of the needle, the pieces are long and most of the haystack is skipped.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{KMismatch, SearchIn};
+//! let needle = KMismatch::new(b"GATTACA", 1);
+//! let haystack = b"TTGATTACATTGACTACAGG";
+//! assert_eq!(vec![(2, 0), (11, 1)], needle.find_with_mismatches_in(haystack).collect::<Vec<_>>());
+//! assert_eq!(vec![2, 11], needle.find_in(haystack).collect::<Vec<usize>>());
+//! ```
+use horspool::{Horspool, HorspoolIter};
+use matches::Match;
+use skip_search::position_after_match;
+use super::SearchIn;
+
+pub struct KMismatch <'a, T: 'a + Clone> {
+    needle: &'a [T],
+    k: usize,
+    // the offset of each piece within the needle, and a searcher for it
+    pieces: Vec<(usize, Horspool<'a, T>)>,
+}
+
+impl <'a, T> KMismatch<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    /// Construct a new search object, which finds the needle with up to `k` mismatches.
+    pub fn new(needle: &'a [T], k: usize) -> KMismatch<'a, T> {
+        let count = k + 1;
+        let pieces = if needle.len() < count {
+            // the pieces would be empty, so every position is a candidate
+            Vec::new()
+        } else {
+            let piece_len = needle.len() / count;
+            let extra = needle.len() % count;
+            let mut offset = 0;
+            (0 .. count).map(|i| {
+                // the first pieces are one longer, so that the lengths add up
+                let len = if i < extra { piece_len + 1 } else { piece_len };
+                let piece = (offset, Horspool::new(&needle[offset .. offset + len]));
+                offset += len;
+                piece
+            }).collect()
+        };
+        KMismatch { needle, k, pieces }
+    }
+
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[T] {
+        self.needle
+    }
+
+    /// The maximum number of mismatches allowed in a match.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns an iterator that will produce the index of each approximate match in the haystack,
+    /// together with the number of mismatches, as `(offset, mismatches)`. This iterator will not
+    /// find overlapping matches.
+    pub fn find_with_mismatches_in<'b>(&'b self, haystack: &'b [T]) -> KMismatchMatches<'b, T> {
+        KMismatchMatches::new(self, haystack, false)
+    }
+
+    /// Returns an iterator that will produce the index of each approximate match in the haystack,
+    /// together with the number of mismatches, as `(offset, mismatches)`. This iterator will find
+    /// overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::KMismatch;
+    /// let needle = KMismatch::new(b"aba", 1);
+    /// let found: Vec<_> = needle.find_overlapping_with_mismatches_in(b"abaaa").collect();
+    /// assert_eq!(vec![(0, 0), (2, 1)], found);
+    /// ```
+    pub fn find_overlapping_with_mismatches_in<'b>(&'b self, haystack: &'b [T]) -> KMismatchMatches<'b, T> {
+        KMismatchMatches::new(self, haystack, true)
+    }
+
+    /// Returns an iterator over the approximate matches in the haystack, as `Match`es whose cost is
+    /// the number of mismatches. This iterator will not find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::KMismatch;
+    /// let needle = KMismatch::new(b"GATTACA", 1);
+    /// let haystack = b"TTGATTACATTGACTACAGG";
+    /// let found: Vec<_> = needle.find_matches_in(haystack).map(|m| (m.slice(haystack), m.cost())).collect();
+    /// assert_eq!(vec![(&b"GATTACA"[..], 0), (&b"GACTACA"[..], 1)], found);
+    /// ```
+    pub fn find_matches_in<'b>(&'b self, haystack: &'b [T]) -> KMismatchMatchIter<'b, T> {
+        KMismatchMatchIter { matches: self.find_with_mismatches_in(haystack) }
+    }
+
+    /// Returns an iterator over the approximate matches in the haystack, as `Match`es whose cost is
+    /// the number of mismatches. This iterator will find overlapping matches.
+    pub fn find_overlapping_matches_in<'b>(&'b self, haystack: &'b [T]) -> KMismatchMatchIter<'b, T> {
+        KMismatchMatchIter { matches: self.find_overlapping_with_mismatches_in(haystack) }
+    }
+
+    // the number of mismatches when the needle is at `position`, if it is no more than `k`
+    fn mismatches_at(&self, haystack: &[T], position: usize) -> Option<usize> {
+        let mut mismatches = 0;
+        for (a, b) in self.needle.iter().zip(&haystack[position .. position + self.needle.len()]) {
+            if a != b {
+                mismatches += 1;
+                if mismatches > self.k {
+                    return None;
+                }
+            }
+        }
+        Some(mismatches)
+    }
+}
+
+
+impl <'a, T> SearchIn<[T]> for KMismatch<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    type Iter<'b> = KMismatchIter<'b, T> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of approximate matches of the needle in
+    /// the haystack. This iterator will not find overlapping matches.
+    fn find_in<'b>(&'b self, haystack: &'b [T]) -> KMismatchIter<'b, T> {
+        KMismatchIter { matches: self.find_with_mismatches_in(haystack) }
+    }
+
+    /// Returns an iterator that will produce the indices of approximate matches of the needle in
+    /// the haystack. This iterator will find overlapping matches.
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> KMismatchIter<'b, T> {
+        KMismatchIter { matches: self.find_overlapping_with_mismatches_in(haystack) }
+    }
+}
+
+
+// the exact matches of one piece of the needle, as the positions of the whole needle that they suggest
+struct PieceMatches <'a, T: 'a + Clone> {
+    offset: usize,
+    matches: HorspoolIter<'a, T>,
+    next: Option<usize>,
+}
+
+impl <'a, T> PieceMatches<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    // skip to the first candidate at or after `position`
+    fn advance_to(&mut self, position: usize) -> Option<usize> {
+        while let Some(candidate) = self.next {
+            if candidate >= position {
+                break;
+            }
+            let offset = self.offset;
+            self.next = self.matches.by_ref()
+                .find(|&found| found >= offset)
+                .map(|found| found - offset);
+        }
+        self.next
+    }
+}
+
+
+/// An iterator over approximate matches, which produces `(offset, mismatches)` pairs.
+pub struct KMismatchMatches <'a, T: 'a + Clone> {
+    searcher: &'a KMismatch<'a, T>,
+    haystack: &'a [T],
+    pieces: Vec<PieceMatches<'a, T>>,
+    position: usize,
+    overlapping_matches: bool,
+}
+
+impl <'a, T> KMismatchMatches<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    fn new(searcher: &'a KMismatch<'a, T>, haystack: &'a [T], overlapping_matches: bool) -> KMismatchMatches<'a, T> {
+        let pieces = searcher.pieces.iter().map(|&(offset, ref piece)| {
+            let mut matches = piece.find_overlapping_in(haystack);
+            let next = matches.by_ref()
+                .find(|&found| found >= offset)
+                .map(|found| found - offset);
+            PieceMatches { offset, matches, next }
+        }).collect();
+        KMismatchMatches { searcher, haystack, pieces, position: 0, overlapping_matches }
+    }
+}
+
+impl <'a, T> Iterator for KMismatchMatches<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    type Item = (usize, usize);
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let len = self.searcher.needle.len();
+        loop {
+            let position = self.position;
+            let candidate = if self.pieces.is_empty() {
+                position
+            } else {
+                self.pieces.iter_mut().filter_map(|piece| piece.advance_to(position)).min()?
+            };
+            if candidate + len > self.haystack.len() {
+                return None;
+            }
+            if let Some(mismatches) = self.searcher.mismatches_at(self.haystack, candidate) {
+                self.position = position_after_match(candidate, len, self.overlapping_matches);
+                return Some((candidate, mismatches));
+            }
+            self.position = candidate + 1;
+        }
+    }
+}
+
+
+pub struct KMismatchIter <'a, T: 'a + Clone> {
+    matches: KMismatchMatches<'a, T>,
+}
+
+impl <'a, T> Iterator for KMismatchIter<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        self.matches.next().map(|(position, _)| position)
+    }
+}
+
+
+/// An iterator over approximate matches, which produces a `Match` for each one.
+pub struct KMismatchMatchIter <'a, T: 'a + Clone> {
+    matches: KMismatchMatches<'a, T>,
+}
+
+impl <'a, T> Iterator for KMismatchMatchIter<'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    type Item = Match;
+    fn next(&mut self) -> Option<Match> {
+        let len = self.matches.searcher.needle.len();
+        self.matches.next().map(|(position, mismatches)| Match::new(position, position + len).with_cost(mismatches))
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+    use test_util::Random;
+
+    fn naive(needle: &[u8], haystack: &[u8], k: usize) -> Vec<(usize, usize)> {
+        haystack.windows(needle.len())
+            .enumerate()
+            .map(|(i, window)| (i, window.iter().zip(needle).filter(|&(a, b)| a != b).count()))
+            .filter(|&(_, mismatches)| mismatches <= k)
+            .collect()
+    }
+
+    #[test]
+    pub fn test_exact() {
+        let needle = KMismatch::new(b"ghi", 0);
+        assert_eq!(vec![(8, 0)], needle.find_with_mismatches_in(b"abc def ghi jkl").collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_matches() {
+        let needle = KMismatch::new(b"aba", 1);
+        let found: Vec<_> = needle.find_overlapping_matches_in(b"abaaa").map(|m| (m.range(), m.cost())).collect();
+        assert_eq!(vec![(0 .. 3, 0), (2 .. 5, 1)], found);
+    }
+
+    #[test]
+    pub fn test_non_overlapping() {
+        let needle = KMismatch::new(b"aaaa", 1);
+        let haystack = b"aaabaaaaa";
+        assert_eq!(vec![(0, 1), (4, 0)], needle.find_with_mismatches_in(haystack).collect::<Vec<_>>());
+        assert_eq!(2, needle.count_in(haystack));
+        assert_eq!(naive(b"aaaa", haystack, 1), needle.find_overlapping_with_mismatches_in(haystack).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_k_at_least_needle_length() {
+        let needle = KMismatch::new(b"ab", 2);
+        assert_eq!(vec![(0, 2), (1, 2), (2, 0)], needle.find_overlapping_with_mismatches_in(b"xxab").collect::<Vec<_>>());
+        assert_eq!(vec![0, 2], needle.find_in(b"xxab").collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_short_haystack() {
+        let needle = KMismatch::new(b"abcd", 1);
+        assert_eq!(None, needle.find_first_in(b"abc"));
+    }
+
+    #[test]
+    pub fn test_dna() {
+        let haystack = b"ACGTTGCATGTCGCATGATGCATGAGAGCTACGTTGCATGTCGCATGATGCATGAGAGCT";
+        for k in 0 .. 4 {
+            let needle = KMismatch::new(b"GCATGATG", k);
+            assert_eq!(naive(b"GCATGATG", haystack, k), needle.find_overlapping_with_mismatches_in(haystack).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    pub fn test_agrees_with_naive() {
+        let mut random = Random::new(99);
+        let haystack: Vec<u8> = (0 .. 2000).map(|_| b'a' + random.below(3) as u8).collect();
+        for _ in 0 .. 100 {
+            let len = 1 + random.below(10) as usize;
+            let k = random.below(4) as usize;
+            let needle: Vec<u8> = (0 .. len).map(|_| b'a' + random.below(3) as u8).collect();
+            let searcher = KMismatch::new(&needle, k);
+            assert_eq!(naive(&needle, &haystack, k), searcher.find_overlapping_with_mismatches_in(&haystack).collect::<Vec<_>>());
+        }
+    }
+}
@@ -1,53 +1,146 @@
-#![feature(specialization)]
-#![cfg_attr(test, feature(test))]
-#![cfg(test)]
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
+#![cfg_attr(feature = "pattern", feature(pattern))]
+#[cfg(all(test, feature = "bench"))]
 extern crate test;
 extern crate memchr;
 
-#[macro_use]
-extern crate log;
-
-#[cfg(test)]
+#[cfg(all(test, feature = "bench"))]
 mod benchmarks;
 
-mod boyer_moore_memchr;
-mod horspool_memchr;
 mod boyer_moore;
+mod bad_chars;
+mod error;
 mod skip_search;
 mod horspool;
-// internal for benchmarks
-mod naive_search;
+mod two_way;
+mod iter_search;
+mod read_search;
+mod str_search;
+mod aho_corasick;
+mod k_mismatch;
+mod myers;
+mod masked;
+mod class_needle;
+mod shift_or;
+mod simd_search;
+mod searcher;
+mod boyer_moore_memchr;
+mod horspool_memchr;
 mod memchr_search;
+mod naive_search;
+mod rare_bytes;
+mod matches;
+mod search_state;
+#[cfg(feature = "pattern")]
+mod pattern;
+#[cfg(test)]
+mod test_util;
 
 pub use boyer_moore::BoyerMoore;
 pub use horspool::Horspool;
+pub use two_way::{TwoWay, TwoWayIter};
+pub use bad_chars::{BadCharsTable, AutoTable, DenseTable, HashedTable};
+pub use error::NeedleError;
+pub use iter_search::SkipSearchIter;
+pub use read_search::ReadSearchIter;
+pub use str_search::{StrSearch, StrSearchIter, StrMatches};
+pub use aho_corasick::{AhoCorasick, AhoCorasickMatches, AhoCorasickMatchIter, AhoCorasickIter, MatchKind};
+pub use k_mismatch::{KMismatch, KMismatchMatches, KMismatchMatchIter, KMismatchIter};
+pub use myers::{Myers, MyersMatches, MyersMatchIter};
+pub use masked::{Masked, MaskedIter};
+pub use class_needle::{ClassNeedle, ClassNeedleIter, SymbolClass};
+pub use shift_or::{ShiftOr, ShiftOrIter, SHIFT_OR_MAX_LEN};
+pub use simd_search::{SimdSearch, SimdSearchIter};
+pub use searcher::{Searcher, SearcherIter, Strategy};
+pub use boyer_moore_memchr::{BoyerMooreMemchr, BoyerMooreMemchrIter};
+pub use horspool_memchr::{HorspoolMemchr, HorspoolMemchrIter};
+pub use memchr_search::{MemchrSearch, MemchrSearchIter};
+pub use naive_search::{NaiveSearch, NaiveSearchIter};
+pub use matches::{Match, MatchIter};
+pub use search_state::SearchState;
+#[cfg(feature = "pattern")]
+pub use pattern::StrPatternSearcher;
 
-pub trait SearchIn<'a, H: ?Sized> {
-    type Iter: Iterator<Item = usize>;
-    fn find_in(&'a self, haystack: &'a H) -> Self::Iter;
-    fn find_overlapping_in(&'a self, haystack: &'a H) -> Self::Iter;
+use std::io::{self, Read};
+
+/// Search for the needle in a haystack. The iterators borrow both the searcher and the haystack,
+/// but the lifetime of the haystack is independent of any lifetime in the searcher's type, so a
+/// long-lived searcher (for example, one that owns its needle) can be used on short-lived buffers.
+pub trait SearchIn<H: ?Sized> {
+    type Iter<'a>: Iterator<Item = usize> where Self: 'a, H: 'a;
+    fn find_in<'a>(&'a self, haystack: &'a H) -> Self::Iter<'a>;
+    fn find_overlapping_in<'a>(&'a self, haystack: &'a H) -> Self::Iter<'a>;
 
     /// Finds the first occurence of the search term in haystack and returns the index if it is found.
-    fn find_first_in(&'a self, haystack: &'a H) -> Option<usize> {
-        self.find_in(&haystack).next()
+    fn find_first_in(&self, haystack: &H) -> Option<usize> {
+        self.find_in(haystack).next()
+    }
+}
+
+/// Search for the needle in a haystack from right to left.
+pub trait ReverseSearchIn<H: ?Sized>: SearchIn<H> {
+    type RevIter<'a>: Iterator<Item = usize> where Self: 'a, H: 'a;
+    fn rfind_in<'a>(&'a self, haystack: &'a H) -> Self::RevIter<'a>;
+    fn rfind_overlapping_in<'a>(&'a self, haystack: &'a H) -> Self::RevIter<'a>;
+
+    /// Finds the last occurence of the search term in haystack and returns the index if it is found.
+    fn find_last_in(&self, haystack: &H) -> Option<usize> {
+        self.rfind_in(haystack).next()
+    }
+}
+
+/// Search for the needle in a haystack, producing each match as a `Match` with its start and end
+/// offsets, rather than only the offset where it starts. Every match is as long as the needle.
+pub trait MatchIn<T>: SearchIn<[T]> {
+    /// The length of the needle, which is the length of every match.
+    fn match_len(&self) -> usize;
+
+    /// Returns an iterator over the matches of the needle in the haystack. Like `find_in`, this
+    /// will not find overlapping matches.
+    fn find_matches_in<'a>(&'a self, haystack: &'a [T]) -> MatchIter<Self::Iter<'a>> {
+        MatchIter::new(self.find_in(haystack), self.match_len())
     }
+
+    /// Returns an iterator over the matches of the needle in the haystack. Like
+    /// `find_overlapping_in`, this will find overlapping matches.
+    fn find_overlapping_matches_in<'a>(&'a self, haystack: &'a [T]) -> MatchIter<Self::Iter<'a>> {
+        MatchIter::new(self.find_overlapping_in(haystack), self.match_len())
+    }
+}
+
+/// Search for the needle in the elements of an iterator, consuming it as the search progresses.
+/// Only a window the size of the needle is buffered, so this can be used on haystacks that are
+/// generated or decoded lazily, without collecting them first.
+pub trait SearchInIter<I: IntoIterator> {
+    type Iter<'a>: Iterator<Item = usize> where Self: 'a;
+    fn find_in_iter(&self, haystack: I) -> Self::Iter<'_>;
+    fn find_overlapping_in_iter(&self, haystack: I) -> Self::Iter<'_>;
+}
+
+/// Search for the needle in the bytes produced by a reader, without loading the whole stream into
+/// memory. Matches are reported as absolute byte offsets from the start of the stream, and any
+/// error returned by the reader is passed on by the iterator.
+pub trait SearchInRead<R: Read> {
+    type Iter<'a>: Iterator<Item = io::Result<usize>> where Self: 'a;
+    fn find_in_read(&self, reader: R) -> Self::Iter<'_>;
+    fn find_overlapping_in_read(&self, reader: R) -> Self::Iter<'_>;
 }
 
-pub trait CountIn<'a, H: ?Sized> {
-    fn count_in(&'a self, haystack: &'a H) -> usize;
-    fn occurs_in(&'a self, haystack: &'a H) -> bool {
-        self.count_in(&haystack) > 0
+pub trait CountIn<H: ?Sized> {
+    fn count_in(&self, haystack: &H) -> usize;
+    fn occurs_in(&self, haystack: &H) -> bool {
+        self.count_in(haystack) > 0
     }
 }
 
-impl <'a, H: ?Sized, S> CountIn<'a, H> for S
-    where S: SearchIn<'a, H>
+impl <H: ?Sized, S> CountIn<H> for S
+    where S: SearchIn<H>
 {
-    fn count_in(&'a self, haystack: &'a H) -> usize {
-        self.find_in(&haystack).count()
+    fn count_in(&self, haystack: &H) -> usize {
+        self.find_in(haystack).count()
     }
 
-    fn occurs_in(&'a self, haystack: &'a H) -> bool {
-        self.find_first_in(&haystack).is_some()
+    fn occurs_in(&self, haystack: &H) -> bool {
+        self.find_first_in(haystack).is_some()
     }
 }
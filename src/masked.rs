@@ -0,0 +1,285 @@
+//! Search for a needle with wildcard positions, which match any symbol of the haystack. This is
+//! the Boyer-Moore-Horspool algorithm, with a bad character table that never skips past the last
+//! wildcard before the end of the needle. The more the needle ends with exact symbols, the longer
+//! the skips.
+//!
+//! Byte needles can also be written as textual signatures, such as `"E8 ?? ?? ?? ?? 48 8B"`.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{Masked, SearchIn};
+//! let needle = Masked::new(&[Some(b'c'), None, Some(b't')]);
+//! let haystack = b"the cat and the cot";
+//! assert_eq!(vec![4, 16], needle.find_in(haystack).collect::<Vec<usize>>());
+//! ```
+use std::borrow::Cow;
+use bad_chars::{BadCharsTable, AutoTable};
+use error::NeedleError;
+use skip_search::*;
+use super::{SearchIn, MatchIn};
+
+pub struct Masked <'a, T: 'a + Clone, B = AutoTable> {
+    // `None` is a wildcard
+    needle: Cow<'a, [Option<T>]>,
+    bad_chars: B,
+}
+
+impl <'a, T> Masked <'a, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    /// Construct a new search object for a needle in which `None` matches any symbol, and
+    /// pre-compute the skip table.
+    pub fn new(needle: &'a [Option<T>]) -> Masked<'a, T> {
+        Masked::from_cow(Cow::Borrowed(needle))
+    }
+}
+
+impl <T> Masked <'static, T>
+    where T: Copy + PartialEq + Into<usize>
+{
+    /// Construct a new search object which owns its needle.
+    pub fn from_vec(needle: Vec<Option<T>>) -> Masked<'static, T> {
+        Masked::from_cow(Cow::Owned(needle))
+    }
+}
+
+impl Masked <'static, u8> {
+    /// Construct a new search object from a textual signature of hex bytes, in which `??` or `?` is
+    /// a wildcard. Tokens are separated by whitespace, and a token can hold several bytes, so
+    /// `"E8 ?? ?? 48 8B"` and `"E8????488B"` are the same signature. Returns an error if a token
+    /// can't be parsed, or if the signature is empty.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Masked, NeedleError, SearchIn};
+    /// let needle = Masked::from_hex("E8 ?? ?? ?? ?? 48 8B").unwrap();
+    /// let code = [0x90, 0xE8, 0x10, 0x20, 0x00, 0x00, 0x48, 0x8B, 0xC3];
+    /// assert_eq!(Some(1), needle.find_first_in(&code));
+    /// assert_eq!(Some(NeedleError::InvalidSignature(3)), Masked::from_hex("E8 4").err());
+    /// ```
+    pub fn from_hex(signature: &str) -> Result<Masked<'static, u8>, NeedleError> {
+        let mut needle = Vec::new();
+        let mut chars = signature.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            // the token runs up to the next whitespace, or the end of the signature
+            let mut end = offset + c.len_utf8();
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let token = &signature[offset .. end];
+            if token == "?" {
+                needle.push(None);
+                continue;
+            }
+            let token = token.as_bytes();
+            if !token.len().is_multiple_of(2) {
+                return Err(NeedleError::InvalidSignature(offset));
+            }
+            for pair in token.chunks(2) {
+                let byte = match (pair[0], pair[1]) {
+                    (b'?', b'?') => None,
+                    (high, low) => match (hex_digit(high), hex_digit(low)) {
+                        (Some(high), Some(low)) => Some(high << 4 | low),
+                        _ => return Err(NeedleError::InvalidSignature(offset)),
+                    },
+                };
+                needle.push(byte);
+            }
+        }
+        if needle.is_empty() {
+            return Err(NeedleError::EmptyNeedle);
+        }
+        Ok(Masked::from_vec(needle))
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|digit| digit as u8)
+}
+
+impl <'a, T, B> Masked <'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    /// Construct a new search object, which uses a specific type of bad character table rather
+    /// than the default `AutoTable`.
+    pub fn with_table(needle: &'a [Option<T>]) -> Masked<'a, T, B> {
+        Masked::from_cow(Cow::Borrowed(needle))
+    }
+
+    fn from_cow(needle: Cow<'a, [Option<T>]>) -> Masked<'a, T, B> {
+        Masked {
+            bad_chars: build_masked_bad_chars_table(&needle),
+            needle,
+        }
+    }
+
+    /// The sequence that this object searches for, where `None` is a wildcard.
+    pub fn needle(&self) -> &[Option<T>] {
+        &self.needle
+    }
+
+    /// Convert into a search object that owns its needle, copying the needle if it was borrowed.
+    pub fn into_owned(self) -> Masked<'static, T, B> {
+        Masked {
+            needle: Cow::Owned(self.needle.into_owned()),
+            ..self
+        }
+    }
+}
+
+
+impl <'a, T, B> SearchIn<[T]> for Masked<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    type Iter<'b> = MaskedIter<'b, T, B> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches.
+    fn find_in<'b>(&'b self, haystack: &'b [T]) -> MaskedIter<'b, T, B> {
+        MaskedIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            overlapping_matches: false,
+        }
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Masked, SearchIn};
+    /// let needle = Masked::from_hex("AA ?? AA").unwrap();
+    /// let haystack = [0xAA, 0x00, 0xAA, 0x01, 0xAA];
+    /// assert_eq!(vec![0, 2], needle.find_overlapping_in(&haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> MaskedIter<'b, T, B> {
+        MaskedIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            overlapping_matches: true,
+        }
+    }
+}
+
+impl <'a, T, B> MatchIn<T> for Masked<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
+impl <'a, T, B> SkipSearch<T> for &'a Masked<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    #[inline]
+    fn skip_offset(&self, bad_char: T, _: usize, _haystack: &[T], _haystack_position: usize) -> usize {
+        self.bad_chars.get(bad_char.into())
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.needle.len()
+    }
+
+    #[inline]
+    fn matches_at(&self, index: usize, c: T) -> bool {
+        self.needle[index].is_none_or(|n| n == c)
+    }
+}
+
+
+pub struct MaskedIter <'a, T: 'a + Clone, B: 'a = AutoTable> {
+    searcher: &'a Masked<'a, T, B>,
+    haystack: &'a [T],
+    position: usize,
+    overlapping_matches: bool,
+}
+
+impl <'a, T, B> Iterator for MaskedIter<'a, T, B>
+    where T: Copy + PartialEq + Into<usize>,
+          B: BadCharsTable
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        find_from_position(&self.searcher, self.haystack, self.position)
+            .inspect(|&position| {
+                self.position = position_after_match(position, self.searcher.needle.len(), self.overlapping_matches);
+            })
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+    use bad_chars::HashedTable;
+    use test_util::{Random, naive_find};
+
+    #[test]
+    pub fn test_wildcards() {
+        let needle = Masked::new(&[Some(b'a'), None, None, Some(b'd')]);
+        let haystack = b"abcd axxd abd aaaad";
+        assert_eq!(vec![0, 5, 15], needle.find_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(3, needle.count_in(haystack));
+    }
+
+    #[test]
+    pub fn test_wildcard_limits_skip() {
+        // the final 'x' is not in the needle, but the wildcard could still match it
+        let needle = Masked::new(&[Some(b'a'), None, Some(b'c')]);
+        assert_eq!(Some(2), needle.find_first_in(b"xxaxc"));
+        let needle = Masked::new(&[None, Some(b'b')]);
+        assert_eq!(vec![0, 1, 2], needle.find_overlapping_in(b"bbbb").collect::<Vec<usize>>());
+    }
+
+    #[test]
+    pub fn test_from_hex() {
+        let needle = Masked::from_hex("E8 ?? ?? ?? ?? 48 8b").unwrap();
+        assert_eq!(&[Some(0xE8), None, None, None, None, Some(0x48), Some(0x8B)][..], needle.needle());
+        assert_eq!(needle.needle(), Masked::from_hex("  E8????\t?  ? 488B ").unwrap().needle());
+        assert_eq!(Some(NeedleError::InvalidSignature(3)), Masked::from_hex("48 8G").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(0)), Masked::from_hex("488").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(5)), Masked::from_hex("48\t\u{a0}8G").err());
+        assert_eq!(Some(NeedleError::InvalidSignature(8)), Masked::from_hex("  48 ?? 8  ").err());
+        assert_eq!(Some(NeedleError::EmptyNeedle), Masked::from_hex(" ").err());
+    }
+
+    #[test]
+    pub fn test_large_alphabet() {
+        let haystack: Vec<u16> = "ŝearch ĥere".encode_utf16().collect();
+        let needle: Masked<u16, HashedTable> = Masked::with_table(&[Some(0x125), None, Some(b'r' as u16)]);
+        assert_eq!(Some(7), needle.find_first_in(&haystack));
+    }
+
+    #[test]
+    pub fn test_agrees_with_naive() {
+        let mut random = Random::new(5);
+        let haystack: Vec<u8> = (0 .. 2000).map(|_| random.below(4) as u8).collect();
+        for _ in 0 .. 200 {
+            let len = 1 + random.below(8) as usize;
+            let needle: Vec<Option<u8>> = (0 .. len)
+                .map(|_| match random.below(5) { 4 => None, c => Some(c as u8) })
+                .collect();
+            let expected = naive_find(&haystack, len, |window| window.iter().zip(&needle).all(|(&c, n)| n.is_none_or(|n| n == c)));
+            let searcher = Masked::new(&needle);
+            assert_eq!(expected, searcher.find_overlapping_in(&haystack).collect::<Vec<usize>>(), "{:?}", needle);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Matches as spans of the haystack. The `SearchIn` iterators produce only the offset where each
+//! match starts, which is enough when every match is as long as the needle. A `Match` also carries
+//! the end of the match, the index of the pattern that matched, and, for approximate searches, the
+//! cost of the match, so matches of different lengths can be handled in the same way.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{BoyerMoore, MatchIn};
+//! let needle = BoyerMoore::new(b"Piper");
+//! let haystack = b"Peter Piper picked a peck of pickled peppers";
+//! let found = needle.find_matches_in(haystack).next().unwrap();
+//! assert_eq!(6 .. 11, found.range());
+//! assert_eq!(b"Piper", found.slice(haystack));
+//! ```
+use std::ops::Range;
+
+/// A match of a needle in a haystack, which spans `start .. end`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Match {
+    start: usize,
+    end: usize,
+    pattern: usize,
+    cost: usize,
+}
+
+impl Match {
+    /// Construct a match of the first pattern, spanning `start .. end`, with a cost of zero.
+    ///
+    /// # Panics
+    /// If `end` is before `start`.
+    pub fn new(start: usize, end: usize) -> Match {
+        assert!(start <= end, "a match can't end before it starts");
+        Match { start, end, pattern: 0, cost: 0 }
+    }
+
+    /// The same match, but of the pattern with the given index.
+    pub fn with_pattern(self, pattern: usize) -> Match {
+        Match { pattern, ..self }
+    }
+
+    /// The same match, but with the given cost.
+    pub fn with_cost(self, cost: usize) -> Match {
+        Match { cost, ..self }
+    }
+
+    /// The offset in the haystack where the match starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The offset in the haystack just after the end of the match.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The offsets of the match, as a range which can be used to index the haystack.
+    pub fn range(&self) -> Range<usize> {
+        self.start .. self.end
+    }
+
+    /// The number of symbols in the match.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether the match is empty, which only happens when the needle is empty.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The index of the pattern that matched. This is always 0 for searchers with a single needle.
+    pub fn pattern(&self) -> usize {
+        self.pattern
+    }
+
+    /// How far the match is from the needle, such as the number of mismatches or the edit distance.
+    /// This is always 0 for exact searches.
+    pub fn cost(&self) -> usize {
+        self.cost
+    }
+
+    /// The part of the haystack that matched.
+    ///
+    /// # Panics
+    /// If the match is not within the haystack, which can happen if it was found in a different
+    /// haystack.
+    pub fn slice<'h, T>(&self, haystack: &'h [T]) -> &'h [T] {
+        &haystack[self.range()]
+    }
+}
+
+
+/// An iterator over the matches of a needle, which are all as long as the needle.
+pub struct MatchIter <I> {
+    len: usize,
+    offsets: I,
+}
+
+impl <I> MatchIter<I> {
+    pub(crate) fn new(offsets: I, len: usize) -> MatchIter<I> {
+        MatchIter { len, offsets }
+    }
+}
+
+impl <I> Iterator for MatchIter<I>
+    where I: Iterator<Item = usize>
+{
+    type Item = Match;
+    fn next(&mut self) -> Option<Match> {
+        self.offsets.next().map(|position| Match::new(position, position + self.len))
+    }
+}
+
+impl <I> DoubleEndedIterator for MatchIter<I>
+    where I: DoubleEndedIterator<Item = usize>
+{
+    fn next_back(&mut self) -> Option<Match> {
+        self.offsets.next_back().map(|position| Match::new(position, position + self.len))
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_match() {
+        let found = Match::new(2, 5).with_pattern(3).with_cost(1);
+        assert_eq!((2, 5, 3, 1), (found.start(), found.end(), found.pattern(), found.cost()));
+        assert_eq!(3, found.len());
+        assert!(!found.is_empty());
+        assert_eq!(b"cde", found.slice(b"abcdefg"));
+        assert!(Match::new(4, 4).is_empty());
+    }
+
+    #[test]
+    pub fn test_match_iter() {
+        let matches: Vec<Match> = MatchIter::new(vec![0, 5].into_iter(), 2).collect();
+        assert_eq!(vec![Match::new(0, 2), Match::new(5, 7)], matches);
+        let reversed: Vec<Match> = MatchIter::new(vec![0, 5].into_iter(), 2).rev().collect();
+        assert_eq!(vec![Match::new(5, 7), Match::new(0, 2)], reversed);
+    }
+}
@@ -1,5 +1,19 @@
+//! A byte searcher which uses `memchr` to find each occurrence of the first byte of the needle,
+//! and then compares the rest of the needle. It needs no tables, so it is cheap to construct, and
+//! it is very fast when the first byte of the needle is rare in the haystack. When that byte is
+//! common, it degrades towards `NaiveSearch`.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{MemchrSearch, SearchIn};
+//! let needle = MemchrSearch::new(b"example");
+//! let haystack = b"This is an example of searching for a word";
+//! assert_eq!(Some(11), needle.find_first_in(haystack));
+//! ```
 use memchr::memchr;
-use super::SearchIn;
+use skip_search::position_after_match;
+use super::{SearchIn, MatchIn};
 
 pub struct MemchrSearch <'a> {
     needle: &'a [u8]
@@ -7,36 +21,70 @@ pub struct MemchrSearch <'a> {
 
 
 impl <'a> MemchrSearch <'a> {
-    pub fn new(needle: &'a [u8]) -> MemchrSearch {
+    /// Construct a new search object. There is nothing to pre-compute.
+    pub fn new(needle: &'a [u8]) -> MemchrSearch<'a> {
         MemchrSearch { 
-            needle: needle
+            needle
         }
     }
-}
 
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[u8] {
+        self.needle
+    }
+}
 
-impl <'a> SearchIn<'a, [u8]> for MemchrSearch<'a> {
-    type Iter = MemchrSearchIter<'a>;
 
-    fn find_in(&'a self, haystack: &'a [u8]) -> MemchrSearchIter<'a> {
+impl <'a> SearchIn<[u8]> for MemchrSearch<'a> {
+    type Iter<'b> = MemchrSearchIter<'b> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches; the first character of a match 
+    /// will start after the last character of the previous match.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{MemchrSearch, SearchIn};
+    /// let needle = MemchrSearch::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_in<'b>(&'b self, haystack: &'b [u8]) -> MemchrSearchIter<'b> {
         MemchrSearchIter {
-            searcher: &self,
-            haystack: haystack,
+            searcher: self,
+            haystack,
             position: 0,
             overlapping_matches: false,
         }
     }
 
-    fn find_overlapping_in(&'a self, haystack: &'a [u8]) -> MemchrSearchIter<'a> {
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches; the first character of a match is 
+    /// allowed to be matched from within the previous match.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{MemchrSearch, SearchIn};
+    /// let needle = MemchrSearch::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [u8]) -> MemchrSearchIter<'b> {
         MemchrSearchIter {
-            searcher: &self,
-            haystack: &haystack,
+            searcher: self,
+            haystack,
             position: 0,
             overlapping_matches: true
         }
     }
 }
 
+impl <'a> MatchIn<u8> for MemchrSearch<'a> {
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
 
 pub struct MemchrSearchIter <'a> {
     searcher: &'a MemchrSearch<'a>,
@@ -47,20 +95,18 @@ pub struct MemchrSearchIter <'a> {
 
 
 fn find_from_position<'a>(&MemchrSearch { needle }: &'a MemchrSearch, haystack: &'a [u8], mut position: usize) -> Option<usize> {
-    while position <= haystack.len() - needle.len() {
-        match memchr(needle[0], &haystack[position ..]) {
-            None => return None,
-            Some(needle_pos) => {
-                let needle_pos = needle_pos + position;
-                if (1 .. needle.len()).all(|needle_index| {
-                    needle[needle_index] == haystack[needle_index + needle_pos]
-                }) {
-                    return Some(needle_pos);
-                } else {
-                    position = needle_pos + 1;
-                }
-            }
+    let first = match needle.first() {
+        Some(&first) => first,
+        None => return if position <= haystack.len() { Some(position) } else { None },
+    };
+    // the last position where the needle could start, if the haystack is long enough to hold it
+    let last = haystack.len().checked_sub(needle.len())?;
+    while position <= last {
+        let needle_pos = memchr(first, &haystack[position ..= last])? + position;
+        if haystack[needle_pos + 1 .. needle_pos + needle.len()] == needle[1 ..] {
+            return Some(needle_pos);
         }
+        position = needle_pos + 1;
     }
     None
 }
@@ -69,14 +115,9 @@ fn find_from_position<'a>(&MemchrSearch { needle }: &'a MemchrSearch, haystack:
 impl <'a> Iterator for MemchrSearchIter<'a> {
     type Item = usize;
     fn next(&mut self) -> Option<usize> {
-        find_from_position(&self.searcher, &self.haystack, self.position)
-            .and_then(|position| {
-                if self.overlapping_matches {
-                    self.position = position + 1;
-                } else {
-                    self.position = position + self.searcher.needle.len();
-                }
-                Some(position)
+        find_from_position(self.searcher, self.haystack, self.position)
+            .inspect(|&position| {
+                self.position = position_after_match(position, self.searcher.needle.len(), self.overlapping_matches);
             })
     }
 }
@@ -102,4 +143,30 @@ pub mod test {
         let haystack = b"acacacababadabacacad";
         assert_eq!(Some(12), MemchrSearch::new(b"abacac").find_first_in(haystack));
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_empty_needle() {
+        let needle = MemchrSearch::new(b"");
+        assert_eq!(vec![0,1,2,3], needle.find_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(vec![0,1,2,3], needle.find_overlapping_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(Some(0), needle.find_first_in(b""));
+    }
+
+    #[test]
+    pub fn test_short_haystack() {
+        let needle = MemchrSearch::new(b"abc");
+        assert_eq!(None, needle.find_first_in(b"ab"));
+        assert_eq!(None, needle.find_first_in(b""));
+        // the first byte occurs, but too close to the end for the rest of the needle to fit
+        assert_eq!(None, needle.find_first_in(b"xxab"));
+        assert_eq!(Some(0), needle.find_first_in(b"abc"));
+    }
+
+    #[test]
+    pub fn test_overlapping() {
+        let needle = MemchrSearch::new(b"aaba");
+        let haystack = b"aabaabaabaabaaba";
+        assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,366 @@
+//! Approximate search under edit distance, using the bit-parallel algorithm of Myers. A match may
+//! differ from the needle by up to `k` substitutions, insertions or deletions.
+//!
+//! Each column of the dynamic programming matrix is encoded as bit vectors of the differences
+//! between adjacent cells, so a whole column is computed with a handful of word operations for
+//! each 64 symbols of the needle. Longer needles are split over several words, with the carry
+//! passed from each word to the next.
+//!
+//! The match table holds a bit vector for every symbol up to the largest one in the needle, so
+//! this is intended for bytes and other small alphabets.
+//!
+//! Matches are found by where they end. `find_start` recovers where a match begins, by aligning
+//! the reversed needle backwards from its end.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::Myers;
+//! let needle = Myers::new(b"identifier", 1);
+//! let haystack = b"unknown identifer in scope";
+//! let (end, distance) = needle.find_with_distance_in(haystack).next().unwrap();
+//! assert_eq!((17, 1), (end, distance));
+//! assert_eq!(Some(8), needle.find_start(haystack, end));
+//! ```
+use matches::Match;
+
+const WORD_BITS: usize = 64;
+
+pub struct Myers <'a, T: 'a> {
+    needle: &'a [T],
+    k: usize,
+    words: usize,
+    // the bit of the last word which is the last row of the matrix
+    last_row: u64,
+    // for each symbol, the positions in the needle where it occurs, `words` at a time
+    peq: Vec<u64>,
+    // the same, for the reversed needle
+    rev_peq: Vec<u64>,
+}
+
+impl <'a, T> Myers<'a, T>
+    where T: Copy + Into<usize>
+{
+    /// Construct a new search object, which finds the needle with an edit distance of at most `k`.
+    pub fn new(needle: &'a [T], k: usize) -> Myers<'a, T> {
+        let len = needle.len();
+        let words = len.div_ceil(WORD_BITS);
+        let symbols = needle.iter().map(|&c| c.into() + 1).max().unwrap_or(0);
+        let mut peq = vec![0; symbols * words];
+        let mut rev_peq = vec![0; symbols * words];
+        for (i, &c) in needle.iter().enumerate() {
+            let c: usize = c.into();
+            peq[c * words + i / WORD_BITS] |= 1 << (i % WORD_BITS);
+            let j = len - 1 - i;
+            rev_peq[c * words + j / WORD_BITS] |= 1 << (j % WORD_BITS);
+        }
+        let last_row = if len == 0 { 0 } else { 1 << ((len - 1) % WORD_BITS) };
+        Myers { needle, k, words, last_row, peq, rev_peq }
+    }
+
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[T] {
+        self.needle
+    }
+
+    /// The maximum edit distance allowed in a match.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns an iterator that will produce the end offset of every match in the haystack,
+    /// together with its edit distance, as `(end, distance)`. The end offset is exclusive, and
+    /// consecutive end offsets are often reported for what is essentially the same match.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::Myers;
+    /// let needle = Myers::new(b"abcd", 1);
+    /// let found: Vec<_> = needle.find_with_distance_in(b"xxabcdxx").collect();
+    /// assert_eq!(vec![(5, 1), (6, 0), (7, 1)], found);
+    /// ```
+    pub fn find_with_distance_in<'b>(&'b self, haystack: &'b [T]) -> MyersMatches<'b, T> {
+        MyersMatches {
+            searcher: self,
+            haystack,
+            position: 0,
+            columns: Columns::new(self.needle.len(), self.words),
+        }
+    }
+
+    /// Returns an iterator over the approximate matches in the haystack, as `Match`es whose cost is
+    /// the edit distance. Each match ends where `find_with_distance_in` reports one, and starts
+    /// where `find_start` finds it, so the same caveat about consecutive matches applies.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::Myers;
+    /// let needle = Myers::new(b"identifier", 1);
+    /// let haystack = b"unknown identifer in scope";
+    /// let found = needle.find_matches_in(haystack).next().unwrap();
+    /// assert_eq!((&b"identifer"[..], 1), (found.slice(haystack), found.cost()));
+    /// ```
+    pub fn find_matches_in<'b>(&'b self, haystack: &'b [T]) -> MyersMatchIter<'b, T> {
+        MyersMatchIter { matches: self.find_with_distance_in(haystack) }
+    }
+
+    /// Finds the start offset of a match which ends at `end`, and which has the smallest edit
+    /// distance of all the matches ending there. Of the starts that are equally close, the one
+    /// nearest to `end` is chosen. Returns `None` if no match ends at `end`.
+    ///
+    /// # Panics
+    /// If `end` is past the end of the haystack.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::Myers;
+    /// let needle = Myers::new(b"abcd", 1);
+    /// assert_eq!(Some(2), needle.find_start(b"xxabdxx", 5));
+    /// assert_eq!(None, needle.find_start(b"xxabdxx", 7));
+    /// ```
+    pub fn find_start(&self, haystack: &[T], end: usize) -> Option<usize> {
+        assert!(end <= haystack.len(), "end {} is past the end of a haystack of length {}", end, haystack.len());
+        let len = self.needle.len();
+        // a match can be no longer than the needle plus k insertions
+        let limit = end.min(len + self.k);
+        let mut columns = Columns::new(len, self.words);
+        let mut best = (columns.score, 0);
+        for i in 0 .. limit {
+            // the top row is anchored at `end`, so every symbol skipped costs one edit
+            columns.advance(self, &self.rev_peq, haystack[end - 1 - i], true);
+            if columns.score < best.0 {
+                best = (columns.score, i + 1);
+            }
+        }
+        if best.0 <= self.k {
+            Some(end - best.1)
+        } else {
+            None
+        }
+    }
+
+    // the positions of `c` in the needle, or the reversed needle, for one word
+    fn eq(&self, peq: &[u64], c: T, word: usize) -> u64 {
+        peq.get(c.into() * self.words + word).cloned().unwrap_or(0)
+    }
+}
+
+
+// The vertical differences of the current column of the matrix, and the value of its last row
+struct Columns {
+    pv: Vec<u64>,
+    mv: Vec<u64>,
+    score: usize,
+}
+
+impl Columns {
+    fn new(len: usize, words: usize) -> Columns {
+        // the first column counts down the needle, so every difference is +1
+        Columns { pv: vec![!0; words], mv: vec![0; words], score: len }
+    }
+
+    // Compute the next column, for the haystack symbol `c`. If `anchored`, the top row also
+    // increases by one, otherwise the match is free to start at any column.
+    fn advance<T>(&mut self, searcher: &Myers<T>, peq: &[u64], c: T, anchored: bool)
+        where T: Copy + Into<usize>
+    {
+        let words = searcher.words;
+        let mut carry = if anchored { 1 } else { 0 };
+        for word in 0 .. words {
+            let high = if word + 1 == words { searcher.last_row } else { 1 << (WORD_BITS - 1) };
+            let eq = searcher.eq(peq, c, word);
+            carry = advance_word(&mut self.pv[word], &mut self.mv[word], eq, carry, high);
+        }
+        if words > 0 {
+            self.score = (self.score as isize + carry as isize) as usize;
+        }
+    }
+}
+
+// Compute one word of the next column, from the horizontal difference coming in at the top. Returns
+// the horizontal difference at the row given by `high`.
+fn advance_word(pv: &mut u64, mv: &mut u64, eq: u64, h_in: i8, high: u64) -> i8 {
+    let mut eq = eq;
+    let xv = eq | *mv;
+    if h_in < 0 {
+        eq |= 1;
+    }
+    let xh = ((eq & *pv).wrapping_add(*pv) ^ *pv) | eq;
+    let mut ph = *mv | !(xh | *pv);
+    let mut mh = *pv & xh;
+    let h_out = if ph & high != 0 {
+        1
+    } else if mh & high != 0 {
+        -1
+    } else {
+        0
+    };
+    ph <<= 1;
+    mh <<= 1;
+    if h_in < 0 {
+        mh |= 1;
+    } else if h_in > 0 {
+        ph |= 1;
+    }
+    *pv = mh | !(xv | ph);
+    *mv = ph & xv;
+    h_out
+}
+
+
+/// An iterator over approximate matches, which produces `(end, distance)` pairs.
+pub struct MyersMatches <'a, T: 'a> {
+    searcher: &'a Myers<'a, T>,
+    haystack: &'a [T],
+    position: usize,
+    columns: Columns,
+}
+
+impl <'a, T> Iterator for MyersMatches<'a, T>
+    where T: Copy + Into<usize>
+{
+    type Item = (usize, usize);
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let searcher = self.searcher;
+        while self.position <= self.haystack.len() {
+            let end = self.position;
+            if end > 0 {
+                self.columns.advance(searcher, &searcher.peq, self.haystack[end - 1], false);
+            }
+            self.position += 1;
+            if self.columns.score <= searcher.k {
+                return Some((end, self.columns.score));
+            }
+        }
+        None
+    }
+}
+
+
+/// An iterator over approximate matches, which produces a `Match` for each one.
+pub struct MyersMatchIter <'a, T: 'a> {
+    matches: MyersMatches<'a, T>,
+}
+
+impl <'a, T> Iterator for MyersMatchIter<'a, T>
+    where T: Copy + Into<usize>
+{
+    type Item = Match;
+    fn next(&mut self) -> Option<Match> {
+        let (end, distance) = self.matches.next()?;
+        let start = self.matches.searcher.find_start(self.matches.haystack, end)
+            .expect("the reversed needle aligns with any match that ends here");
+        Some(Match::new(start, end).with_cost(distance))
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use test_util::Random;
+
+    fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+        let mut row: Vec<usize> = (0 ..= b.len()).collect();
+        for (i, &x) in a.iter().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, &y) in b.iter().enumerate() {
+                let cost = if x == y { diagonal } else { diagonal + 1 };
+                diagonal = row[j + 1];
+                row[j + 1] = cost.min(row[j] + 1).min(row[j + 1] + 1);
+            }
+        }
+        row[b.len()]
+    }
+
+    // the dynamic programming search of Sellers, one column at a time
+    fn naive(needle: &[u8], haystack: &[u8], k: usize) -> Vec<(usize, usize)> {
+        let mut column: Vec<usize> = (0 ..= needle.len()).collect();
+        let mut found = Vec::new();
+        for end in 0 ..= haystack.len() {
+            if end > 0 {
+                let mut diagonal = column[0];
+                for (i, &c) in needle.iter().enumerate() {
+                    let cost = if c == haystack[end - 1] { diagonal } else { diagonal + 1 };
+                    diagonal = column[i + 1];
+                    column[i + 1] = cost.min(column[i] + 1).min(column[i + 1] + 1);
+                }
+            }
+            if column[needle.len()] <= k {
+                found.push((end, column[needle.len()]));
+            }
+        }
+        found
+    }
+
+    #[test]
+    pub fn test_exact() {
+        let needle = Myers::new(b"ghi", 0);
+        assert_eq!(vec![(11, 0)], needle.find_with_distance_in(b"abc def ghi jkl").collect::<Vec<_>>());
+        assert_eq!(Some(8), needle.find_start(b"abc def ghi jkl", 11));
+    }
+
+    #[test]
+    pub fn test_insertion_and_deletion() {
+        let needle = Myers::new(b"needle", 1);
+        assert_eq!(Some((9, 1)), needle.find_with_distance_in(b"a needdle").last());
+        assert_eq!(Some(2), needle.find_start(b"a needdle", 9));
+        assert_eq!(Some(2), needle.find_start(b"a nedle", 7));
+    }
+
+    #[test]
+    pub fn test_short_needle() {
+        let needle = Myers::new(b"ab", 2);
+        assert_eq!(naive(b"ab", b"xyz", 2), needle.find_with_distance_in(b"xyz").collect::<Vec<_>>());
+        assert_eq!(Some(0), needle.find_with_distance_in(b"xyz").next().map(|(end, _)| end));
+        let needle = Myers::new(b"", 0);
+        assert_eq!(vec![(0, 0), (1, 0), (2, 0)], needle.find_with_distance_in(b"ab").collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_long_needle() {
+        let mut random = Random::new(42);
+        let haystack: Vec<u8> = (0 .. 1000).map(|_| b'a' + random.below(4) as u8).collect();
+        let mut needle = haystack[300 .. 450].to_vec();
+        needle.remove(70);
+        needle[100] = b'z';
+        needle.insert(140, b'y');
+        let searcher = Myers::new(&needle, 3);
+        assert_eq!(naive(&needle, &haystack, 3), searcher.find_with_distance_in(&haystack).collect::<Vec<_>>());
+        assert!(searcher.find_with_distance_in(&haystack).any(|found| found == (450, 3)));
+        assert_eq!(Some(300), searcher.find_start(&haystack, 450));
+    }
+
+    #[test]
+    pub fn test_agrees_with_naive() {
+        let mut random = Random::new(7);
+        let haystack: Vec<u8> = (0 .. 500).map(|_| b'a' + random.below(4) as u8).collect();
+        for _ in 0 .. 100 {
+            let len = 1 + random.below(140) as usize;
+            let k = random.below(len as u32 / 4 + 2) as usize;
+            let needle: Vec<u8> = (0 .. len).map(|_| b'a' + random.below(4) as u8).collect();
+            let searcher = Myers::new(&needle, k);
+            let found: Vec<_> = searcher.find_with_distance_in(&haystack).collect();
+            assert_eq!(naive(&needle, &haystack, k), found);
+            for &(end, distance) in &found {
+                let start = searcher.find_start(&haystack, end).unwrap();
+                assert_eq!(distance, edit_distance(&needle, &haystack[start .. end]));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end")]
+    pub fn test_find_start_past_end() {
+        Myers::new(b"abcd", 1).find_start(b"xxabcd", 7);
+    }
+
+    #[test]
+    pub fn test_matches() {
+        let needle = Myers::new(b"abcd", 1);
+        let haystack = b"xxabdxxabcdx";
+        let found: Vec<_> = needle.find_matches_in(haystack).map(|m| (m.slice(haystack), m.cost())).collect();
+        assert_eq!(vec![(&b"abd"[..], 1), (&b"abc"[..], 1), (&b"abcd"[..], 0), (&b"abcdx"[..], 1)], found);
+    }
+}
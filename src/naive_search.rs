@@ -1,4 +1,18 @@
-use super::SearchIn;
+//! The simplest possible search, which compares the needle at every position of the haystack. It
+//! needs no pre-computation and takes `O(n * m)` time in the worst case, but for very short needles
+//! and haystacks the simplicity can pay off. It is also useful as a reference for the other
+//! searches.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{NaiveSearch, SearchIn};
+//! let needle = NaiveSearch::new(b"example");
+//! let haystack = b"This is an example of searching for a word";
+//! assert_eq!(Some(11), needle.find_first_in(haystack));
+//! ```
+use skip_search::position_after_match;
+use super::{SearchIn, MatchIn};
 
 pub struct NaiveSearch <'a> {
     needle: &'a [u8]
@@ -6,36 +20,70 @@ pub struct NaiveSearch <'a> {
 
 
 impl <'a> NaiveSearch <'a> {
-    pub fn new(needle: &'a [u8]) -> NaiveSearch {
+    /// Construct a new search object. There is nothing to pre-compute.
+    pub fn new(needle: &'a [u8]) -> NaiveSearch<'a> {
         NaiveSearch { 
-            needle: needle
+            needle
         }
     }
-}
 
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[u8] {
+        self.needle
+    }
+}
 
-impl <'a> SearchIn<'a, [u8]> for NaiveSearch<'a> {
-    type Iter = NaiveSearchIter<'a>;
 
-    fn find_in(&'a self, haystack: &'a [u8]) -> NaiveSearchIter<'a> {
+impl <'a> SearchIn<[u8]> for NaiveSearch<'a> {
+    type Iter<'b> = NaiveSearchIter<'b> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches; the first character of a match 
+    /// will start after the last character of the previous match.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{NaiveSearch, SearchIn};
+    /// let needle = NaiveSearch::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_in<'b>(&'b self, haystack: &'b [u8]) -> NaiveSearchIter<'b> {
         NaiveSearchIter {
-            searcher: &self,
-            haystack: haystack,
+            searcher: self,
+            haystack,
             position: 0,
             overlapping_matches: false,
         }
     }
 
-    fn find_overlapping_in(&'a self, haystack: &'a [u8]) -> NaiveSearchIter<'a> {
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches; the first character of a match is 
+    /// allowed to be matched from within the previous match.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{NaiveSearch, SearchIn};
+    /// let needle = NaiveSearch::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [u8]) -> NaiveSearchIter<'b> {
         NaiveSearchIter {
-            searcher: &self,
-            haystack: &haystack,
+            searcher: self,
+            haystack,
             position: 0,
             overlapping_matches: true
         }
     }
 }
 
+impl <'a> MatchIn<u8> for NaiveSearch<'a> {
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
 
 pub struct NaiveSearchIter <'a> {
     searcher: &'a NaiveSearch<'a>,
@@ -47,7 +95,7 @@ pub struct NaiveSearchIter <'a> {
 
 
 fn find_from_position<'a>(&NaiveSearch { needle }: &'a NaiveSearch, haystack: &'a [u8], position: usize) -> Option<usize> {
-    (position .. haystack.len() - needle.len() + 1)
+    (position .. (haystack.len() + 1).saturating_sub(needle.len()))
         .find( |needle_pos| {
             (0 .. needle.len()).all(|needle_index| {
                 needle[needle_index] == haystack[needle_index + needle_pos]
@@ -59,14 +107,9 @@ fn find_from_position<'a>(&NaiveSearch { needle }: &'a NaiveSearch, haystack: &'
 impl <'a> Iterator for NaiveSearchIter<'a> {
     type Item = usize;
     fn next(&mut self) -> Option<usize> {
-        find_from_position(&self.searcher, &self.haystack, self.position)
-            .and_then(|position| {
-                if self.overlapping_matches {
-                    self.position = position + 1;
-                } else {
-                    self.position = position + self.searcher.needle.len();
-                }
-                Some(position)
+        find_from_position(self.searcher, self.haystack, self.position)
+            .inspect(|&position| {
+                self.position = position_after_match(position, self.searcher.needle.len(), self.overlapping_matches);
             })
     }
 }
@@ -92,4 +135,28 @@ pub mod test {
         let haystack = b"acacacababadabacacad";
         assert_eq!(Some(12), NaiveSearch::new(b"abacac").find_first_in(haystack));
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_empty_needle() {
+        let needle = NaiveSearch::new(b"");
+        assert_eq!(vec![0,1,2,3], needle.find_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(vec![0,1,2,3], needle.find_overlapping_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(Some(0), needle.find_first_in(b""));
+    }
+
+    #[test]
+    pub fn test_short_haystack() {
+        let needle = NaiveSearch::new(b"abc");
+        assert_eq!(None, needle.find_first_in(b"ab"));
+        assert_eq!(None, needle.find_first_in(b""));
+        assert_eq!(Some(0), needle.find_first_in(b"abc"));
+    }
+
+    #[test]
+    pub fn test_overlapping() {
+        let needle = NaiveSearch::new(b"aaba");
+        let haystack = b"aabaabaabaabaaba";
+        assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
+    }
+}
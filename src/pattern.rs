@@ -0,0 +1,279 @@
+//! Integration with the standard library's (unstable) `Pattern` API, so that a precompiled
+//! `StrSearch` can be used with `str::find`, `str::split`, `str::matches`, `str::replace`,
+//! `str::trim_start_matches` and the rest. Requires the `pattern` cargo feature, and nightly Rust.
+//!
+//! # Examples
+//!
+//! ```
+//! # #![feature(pattern)]
+//! # #[cfg(feature = "pattern")] {
+//! use needle::Horspool;
+//! let comma = Horspool::new_str(", ");
+//! let text = "one, two, three";
+//! assert_eq!(Some(3), text.find(&comma));
+//! assert_eq!(vec!["one", "two", "three"], text.split(&comma).collect::<Vec<&str>>());
+//! assert_eq!("one; two; three", text.replace(&comma, "; "));
+//! # }
+//! ```
+use std::str::pattern::{Pattern, Searcher, ReverseSearcher, SearchStep};
+use str_search::StrSearch;
+use super::{SearchIn, ReverseSearchIn};
+
+impl <'b, S> Pattern for &'b StrSearch<S>
+    where S: SearchIn<[u8]>
+{
+    type Searcher<'h> = StrPatternSearcher<'h, 'b, S>;
+
+    fn into_searcher(self, haystack: &str) -> StrPatternSearcher<'_, 'b, S> {
+        StrPatternSearcher {
+            haystack,
+            searcher: self,
+            position: 0,
+            end: haystack.len(),
+            empty_matched_fw: false,
+            empty_matched_bw: false,
+        }
+    }
+}
+
+/// The `Searcher` for a `StrSearch` used as a `Pattern`. Matches do not overlap. Searching from
+/// the back finds matches greedily from the right, so if the needle can overlap with itself, the
+/// matches found from the front and back may differ. An empty needle matches at every char
+/// boundary, as the empty `&str` pattern does.
+pub struct StrPatternSearcher <'h, 'b, S: 'b> {
+    haystack: &'h str,
+    searcher: &'b StrSearch<S>,
+    // the part of the haystack which has not been searched yet, from either end
+    position: usize,
+    end: usize,
+    // whether an empty needle has already matched at `position` or `end`
+    empty_matched_fw: bool,
+    empty_matched_bw: bool,
+}
+
+impl <'h, 'b, S> StrPatternSearcher<'h, 'b, S>
+    where S: SearchIn<[u8]>
+{
+    fn remaining(&self) -> &'h [u8] {
+        &self.haystack.as_bytes()[self.position .. self.end]
+    }
+
+    // An empty needle matches at each char boundary, and the chars in between are rejected
+    fn next_empty(&mut self) -> SearchStep {
+        if self.position > self.end {
+            return SearchStep::Done;
+        }
+        let start = self.position;
+        if !self.empty_matched_fw {
+            self.empty_matched_fw = true;
+            return SearchStep::Match(start, start);
+        }
+        self.empty_matched_fw = false;
+        match self.haystack[start .. self.end].chars().next() {
+            Some(c) => {
+                self.position += c.len_utf8();
+                SearchStep::Reject(start, self.position)
+            }
+            None => {
+                self.position = self.end + 1;
+                SearchStep::Done
+            }
+        }
+    }
+
+    fn next_empty_back(&mut self) -> SearchStep {
+        if self.position > self.end {
+            return SearchStep::Done;
+        }
+        let end = self.end;
+        if !self.empty_matched_bw {
+            self.empty_matched_bw = true;
+            return SearchStep::Match(end, end);
+        }
+        self.empty_matched_bw = false;
+        match self.haystack[self.position .. end].chars().next_back() {
+            Some(c) => {
+                self.end -= c.len_utf8();
+                SearchStep::Reject(self.end, end)
+            }
+            None => {
+                self.position = end + 1;
+                SearchStep::Done
+            }
+        }
+    }
+}
+
+unsafe impl <'h, 'b, S> Searcher<'h> for StrPatternSearcher<'h, 'b, S>
+    where S: SearchIn<[u8]>
+{
+    fn haystack(&self) -> &'h str {
+        self.haystack
+    }
+
+    fn next(&mut self) -> SearchStep {
+        if self.searcher.len() == 0 {
+            return self.next_empty();
+        }
+        if self.position >= self.end {
+            return SearchStep::Done;
+        }
+        let start = self.position;
+        match self.searcher.as_bytes().find_first_in(self.remaining()) {
+            Some(0) => {
+                self.position += self.searcher.len();
+                SearchStep::Match(start, self.position)
+            }
+            Some(offset) => {
+                self.position += offset;
+                SearchStep::Reject(start, self.position)
+            }
+            None => {
+                self.position = self.end;
+                SearchStep::Reject(start, self.end)
+            }
+        }
+    }
+
+    fn next_match(&mut self) -> Option<(usize, usize)> {
+        if self.searcher.len() == 0 {
+            return loop {
+                match self.next_empty() {
+                    SearchStep::Match(start, end) => break Some((start, end)),
+                    SearchStep::Done => break None,
+                    SearchStep::Reject(..) => {}
+                }
+            };
+        }
+        if self.position >= self.end {
+            return None;
+        }
+        match self.searcher.as_bytes().find_first_in(self.remaining()) {
+            Some(offset) => {
+                let start = self.position + offset;
+                self.position = start + self.searcher.len();
+                Some((start, self.position))
+            }
+            None => {
+                self.position = self.end;
+                None
+            }
+        }
+    }
+}
+
+unsafe impl <'h, 'b, S> ReverseSearcher<'h> for StrPatternSearcher<'h, 'b, S>
+    where S: ReverseSearchIn<[u8]>
+{
+    fn next_back(&mut self) -> SearchStep {
+        if self.searcher.len() == 0 {
+            return self.next_empty_back();
+        }
+        if self.position >= self.end {
+            return SearchStep::Done;
+        }
+        let end = self.end;
+        match self.searcher.as_bytes().find_last_in(self.remaining()) {
+            Some(offset) => {
+                let match_end = self.position + offset + self.searcher.len();
+                if match_end == end {
+                    self.end = self.position + offset;
+                    SearchStep::Match(self.end, end)
+                } else {
+                    self.end = match_end;
+                    SearchStep::Reject(match_end, end)
+                }
+            }
+            None => {
+                self.end = self.position;
+                SearchStep::Reject(self.position, end)
+            }
+        }
+    }
+
+    fn next_match_back(&mut self) -> Option<(usize, usize)> {
+        if self.searcher.len() == 0 {
+            return loop {
+                match self.next_empty_back() {
+                    SearchStep::Match(start, end) => break Some((start, end)),
+                    SearchStep::Done => break None,
+                    SearchStep::Reject(..) => {}
+                }
+            };
+        }
+        if self.position >= self.end {
+            return None;
+        }
+        match self.searcher.as_bytes().find_last_in(self.remaining()) {
+            Some(offset) => {
+                self.end = self.position + offset;
+                Some((self.end, self.end + self.searcher.len()))
+            }
+            None => {
+                self.end = self.position;
+                None
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::super::{BoyerMoore, Horspool};
+
+    #[test]
+    pub fn test_find() {
+        let needle = BoyerMoore::new_str("ghi");
+        assert_eq!(Some(8), "abc def ghi jkl".find(&needle));
+        assert_eq!(None, "abc def gh".find(&needle));
+        assert!("abc def ghi jkl".contains(&needle));
+    }
+
+    #[test]
+    pub fn test_rfind() {
+        let needle = Horspool::new_str("/");
+        assert_eq!(Some(8), "/usr/bin/ñandú".rfind(&needle));
+    }
+
+    #[test]
+    pub fn test_split() {
+        let needle = Horspool::new_str("¦¦");
+        let text = "a¦¦bb¦¦¦¦ccc¦¦";
+        assert_eq!(vec!["a", "bb", "", "ccc", ""], text.split(&needle).collect::<Vec<&str>>());
+        assert_eq!(vec!["", "ccc", "", "bb", "a"], text.rsplit(&needle).collect::<Vec<&str>>());
+    }
+
+    #[test]
+    pub fn test_matches() {
+        let needle = BoyerMoore::new_str("sea");
+        let text = "She sells sea shells on the sea shore.";
+        assert_eq!(vec![(10, "sea"), (28, "sea")], text.match_indices(&needle).collect::<Vec<_>>());
+        assert_eq!(vec![(28, "sea"), (10, "sea")], text.rmatch_indices(&needle).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_replace() {
+        let needle = BoyerMoore::new_str("aa");
+        assert_eq!("bba", "aaaaa".replacen(&needle, "b", 2));
+        assert_eq!("xxa", "aaaaa".replace(&needle, "x"));
+    }
+
+    #[test]
+    pub fn test_empty_needle() {
+        let needle = Horspool::new_str("");
+        let text = "añb";
+        assert_eq!(vec![0, 1, 3, 4], text.match_indices(&needle).map(|(i, _)| i).collect::<Vec<usize>>());
+        assert_eq!(vec![4, 3, 1, 0], text.rmatch_indices(&needle).map(|(i, _)| i).collect::<Vec<usize>>());
+        assert_eq!(text.split("").collect::<Vec<&str>>(), text.split(&needle).collect::<Vec<&str>>());
+        assert_eq!("-a-ñ-b-", text.replace(&needle, "-"));
+        assert_eq!(Some(0), "".find(&needle));
+    }
+
+    #[test]
+    pub fn test_trim() {
+        let needle = BoyerMoore::new_str("ab");
+        assert_eq!("cab", "ababcab".trim_start_matches(&needle));
+        assert_eq!("ababc", "ababcabab".trim_end_matches(&needle));
+    }
+}
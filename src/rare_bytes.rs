@@ -0,0 +1,106 @@
+//! Needle analysis for the `memchr` prefilter of `BoyerMooreMemchr` and `HorspoolMemchr`. Rather
+//! than always looking for the last byte of the needle, which may be very common, the prefilter
+//! looks for the rarest byte of the needle, judged by a static table of how often each byte
+//! appears in typical text and code, and checks the second rarest before reporting a candidate.
+//! `Searcher` also uses the rarest byte to decide whether the prefilter is worthwhile.
+use memchr::memchr;
+
+pub struct RareBytes {
+    // the offset in the needle of the rarest byte, and the byte
+    rarest: (usize, u8),
+    // the same, for the rarest byte at any other offset
+    second: (usize, u8),
+}
+
+impl RareBytes {
+    /// Choose the two rarest bytes of a needle. An empty needle is never searched for with a
+    /// prefilter, so any bytes will do.
+    pub fn new(needle: &[u8]) -> RareBytes {
+        if needle.is_empty() {
+            return RareBytes { rarest: (0, 0), second: (0, 0) };
+        }
+        let rarest = rarest_byte(needle, None);
+        let second = if needle.len() > 1 { rarest_byte(needle, Some(rarest.0)) } else { rarest };
+        RareBytes { rarest, second }
+    }
+
+    /// How common the rarest byte of the needle is, from 0 (rarest) to 255 (most common).
+    pub fn rarest_frequency(&self) -> u8 {
+        BYTE_FREQUENCIES[self.rarest.1 as usize]
+    }
+
+    /// The first position at or after `position` where a needle of length `len` could start,
+    /// because both of its rare bytes are in place.
+    pub fn find_candidate(&self, haystack: &[u8], position: usize, len: usize) -> Option<usize> {
+        let (offset, byte) = self.rarest;
+        let (second_offset, second_byte) = self.second;
+        let mut from = position + offset;
+        loop {
+            let found = from + memchr(byte, haystack.get(from ..)?)?;
+            let candidate = found - offset;
+            if candidate + len > haystack.len() {
+                return None;
+            }
+            if haystack[candidate + second_offset] == second_byte {
+                return Some(candidate);
+            }
+            from = found + 1;
+        }
+    }
+}
+
+// The least common byte of the needle, other than at `exclude`. Of equally rare bytes, the
+// last one is chosen, as the bad character rule already favours the end of the needle.
+fn rarest_byte(needle: &[u8], exclude: Option<usize>) -> (usize, u8) {
+    needle.iter()
+        .cloned()
+        .enumerate()
+        .filter(|&(i, _)| Some(i) != exclude)
+        .min_by_key(|&(i, c)| (BYTE_FREQUENCIES[c as usize], !i))
+        .expect("the needle is not empty")
+}
+
+// How common each byte is in typical English text and source code, from 0 (rarest) to 255 (most
+// common). Bytes above 127 and control characters are assumed to be rare.
+static BYTE_FREQUENCIES: [u8; 256] = [
+    229, 155, 154, 153, 152, 151, 150, 149, 148, 190, 240, 147, 146, 189, 145, 144,
+    143, 142, 141, 140, 139, 138, 137, 136, 135, 134, 133, 132, 131, 130, 129, 128,
+    255, 176, 200, 172, 163, 164, 173, 199, 197, 196, 174, 165, 233, 198, 232, 194,
+    228, 227, 226, 225, 224, 223, 222, 221, 220, 219, 193, 192, 171, 191, 170, 175,
+    162, 216, 212, 213, 208, 205, 204, 203, 210, 215, 180, 181, 202, 214, 206, 201,
+    211, 179, 207, 217, 218, 184, 183, 209, 178, 182, 177, 169, 160, 168, 159, 195,
+    158, 252, 234, 243, 244, 254, 239, 237, 246, 250, 187, 230, 245, 241, 249, 251,
+    238, 186, 247, 248, 253, 242, 231, 236, 188, 235, 185, 167, 161, 166, 157, 127,
+    126, 125, 124, 123, 122, 121, 120, 119, 118, 117, 116, 115, 114, 113, 112, 111,
+    110, 109, 108, 107, 106, 105, 104, 103, 102, 101, 100,  99,  98,  97,  96,  95,
+     94,  93,  92,  91,  90,  89,  88,  87,  86,  85,  84,  83,  82,  81,  80,  79,
+     78,  77,  76,  75,  74,  73,  72,  71,  70,  69,  68,  67,  66,  65,  64,  63,
+     62,  61,  60,  59,  58,  57,  56,  55,  54,  53,  52,  51,  50,  49,  48,  47,
+     46,  45,  44,  43,  42,  41,  40,  39,  38,  37,  36,  35,  34,  33,  32,  31,
+     30,  29,  28,  27,  26,  25,  24,  23,  22,  21,  20,  19,  18,  17,  16,  15,
+     14,  13,  12,  11,  10,   9,   8,   7,   6,   5,   4,   3,   2,   1,   0, 156,
+];
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_rarest_bytes() {
+        let rare = RareBytes::new(b"the quiz ");
+        assert_eq!((7, b'z'), rare.rarest);
+        assert_eq!((4, b'q'), rare.second);
+        let rare = RareBytes::new(b"x");
+        assert_eq!((0, b'x'), rare.rarest);
+        assert_eq!((0, b'x'), rare.second);
+    }
+
+    #[test]
+    pub fn test_find_candidate() {
+        let rare = RareBytes::new(b"quiz");
+        assert_eq!(Some(12), rare.find_candidate(b"quit quiet, quiz", 0, 4));
+        assert_eq!(None, rare.find_candidate(b"quit quiet, quiz", 13, 4));
+        assert_eq!(None, rare.find_candidate(b"qu", 0, 4));
+    }
+}
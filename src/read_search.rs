@@ -0,0 +1,158 @@
+//! Streaming search over the bytes produced by a `std::io::Read`. The haystack is read into an
+//! internal buffer in chunks, and the last `needle.len() - 1` bytes of each chunk are carried
+//! over to the next, so that matches which span a chunk boundary are still found.
+use std::cmp::{max, min};
+use std::io::{self, Read, ErrorKind};
+use skip_search::*;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct ReadSearchIter <N, R> {
+    needle: N,
+    reader: R,
+    buffer: Vec<u8>,
+    chunk_size: usize,
+    // absolute offset of the start of the buffer within the stream
+    offset: usize,
+    // search position relative to the start of the buffer
+    position: usize,
+    overlapping_matches: bool,
+    finished: bool,
+}
+
+impl <N, R> ReadSearchIter<N, R>
+    where N: SkipSearch<u8>,
+          R: Read
+{
+    pub fn new(needle: N, reader: R, overlapping_matches: bool) -> ReadSearchIter<N, R> {
+        ReadSearchIter::with_chunk_size(needle, reader, overlapping_matches, CHUNK_SIZE)
+    }
+
+    /// Create a streaming search that reads up to `chunk_size` bytes from the reader at a time.
+    pub fn with_chunk_size(needle: N, reader: R, overlapping_matches: bool, chunk_size: usize) -> ReadSearchIter<N, R> {
+        let chunk_size = max(chunk_size, needle.len());
+        ReadSearchIter {
+            needle,
+            reader,
+            buffer: Vec::with_capacity(chunk_size),
+            chunk_size,
+            offset: 0,
+            position: 0,
+            overlapping_matches,
+            finished: false,
+        }
+    }
+
+    // Drop everything from the buffer that can no longer be part of a match, and read the next chunk.
+    fn refill(&mut self) -> io::Result<()> {
+        let tail_start = self.buffer.len().saturating_sub(self.needle.len().saturating_sub(1));
+        let keep_from = min(self.buffer.len(), max(self.position, tail_start));
+        self.buffer.drain(.. keep_from);
+        self.offset += keep_from;
+        self.position = self.position.saturating_sub(keep_from);
+
+        let start = self.buffer.len();
+        self.buffer.resize(start + self.chunk_size, 0);
+        loop {
+            match self.reader.read(&mut self.buffer[start ..]) {
+                Ok(n) => {
+                    self.buffer.truncate(start + n);
+                    self.finished = n == 0;
+                    return Ok(());
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.buffer.truncate(start);
+                    self.finished = true;
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+impl <N, R> Iterator for ReadSearchIter<N, R>
+    where N: SkipSearch<u8>,
+          R: Read
+{
+    type Item = io::Result<usize>;
+    fn next(&mut self) -> Option<io::Result<usize>> {
+        loop {
+            if self.buffer.len() >= self.needle.len() {
+                if let Some(position) = find_from_position(&self.needle, &self.buffer, self.position) {
+                    self.position = position_after_match(position, self.needle.len(), self.overlapping_matches);
+                    return Some(Ok(self.offset + position));
+                }
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.refill() {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use std::io::{self, Read};
+    use super::*;
+    use super::super::{BoyerMoore, Horspool, SearchInRead};
+
+    // A reader that hands out its data a few bytes at a time
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl <'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = min(min(self.step, buf.len()), self.data.len());
+            buf[.. n].copy_from_slice(&self.data[.. n]);
+            self.data = &self.data[n ..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    pub fn test_find_in_read() {
+        let needle = BoyerMoore::new(b"xyz");
+        let haystack = &b"01xyzxyz890xyz45xyz"[..];
+        let found: Vec<usize> = needle.find_in_read(haystack).map(Result::unwrap).collect();
+        assert_eq!(vec![2,5,11,16], found);
+    }
+
+    #[test]
+    pub fn test_overlapping_across_chunks() {
+        let needle = Horspool::new(b"aaba");
+        let reader = Trickle { data: b"aabaabaabaabaaba", step: 3 };
+        let found: Vec<usize> = ReadSearchIter::with_chunk_size(&needle, reader, true, 1)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![0,3,6,9,12], found);
+    }
+
+    #[test]
+    pub fn test_non_overlapping_across_chunks() {
+        let needle = BoyerMoore::new(b"aaba");
+        let reader = Trickle { data: b"aabaabaabaabaaba", step: 5 };
+        let found: Vec<usize> = ReadSearchIter::with_chunk_size(&needle, reader, false, 5)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![0,6,12], found);
+    }
+
+    #[test]
+    pub fn test_absolute_offsets() {
+        let needle = BoyerMoore::new(b"needle");
+        let mut data = vec![b'.'; 100_000];
+        data.extend_from_slice(b"needle");
+        data.extend(vec![b'.'; 100_000]);
+        data.extend_from_slice(b"needle");
+        let reader = Trickle { data: &data, step: 4096 };
+        let found: Vec<usize> = needle.find_in_read(reader).map(Result::unwrap).collect();
+        assert_eq!(vec![100_000, 200_006], found);
+    }
+}
@@ -0,0 +1,47 @@
+//! The progress of a search through a haystack, which can be saved from an iterator and used to
+//! carry on the same search later, for example after pausing a long scan of a large buffer. The
+//! state holds only offsets, so it doesn't borrow the haystack or the searcher.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{BoyerMoore, SearchIn};
+//! let needle = BoyerMoore::new(b"ab");
+//! let haystack = b"ab ab ab ab";
+//! let mut matches = needle.find_in(haystack);
+//! assert_eq!(Some(0), matches.next());
+//! let state = matches.state();
+//! // later, with the same searcher and haystack
+//! let rest: Vec<usize> = needle.resume_in(haystack, state).collect();
+//! assert_eq!(vec![3, 6, 9], rest);
+//! ```
+
+/// A snapshot of a search iterator. It should only be used to resume a search for the same needle
+/// in the same haystack.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SearchState {
+    pub(crate) position: usize,
+    pub(crate) end: usize,
+    // the part of the needle already known to match at `position`, or before `end`, after an
+    // overlapping match
+    pub(crate) memory: usize,
+    pub(crate) rev_memory: usize,
+    pub(crate) overlapping_matches: bool,
+}
+
+impl SearchState {
+    /// The offset where searching forwards will carry on from.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The offset beyond which no match will be found. Searching backwards carries on from here.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Whether the search finds overlapping matches.
+    pub fn is_overlapping(&self) -> bool {
+        self.overlapping_matches
+    }
+}
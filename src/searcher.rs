@@ -0,0 +1,204 @@
+//! A byte searcher which looks at the needle and chooses the search algorithm that is likely to be
+//! fastest for it, so that callers don't need to know the trade-offs between them:
+//!
+//! * A needle of one byte is found with `memchr`.
+//! * A needle containing a byte that is rare in typical text uses `HorspoolMemchr`, whose `memchr`
+//!   prefilter jumps straight to the places where that byte occurs.
+//! * Otherwise, a periodic needle, such as `"abababab"`, uses `BoyerMoore`, whose Galil rule keeps
+//!   searches for overlapping matches linear.
+//! * Anything else uses `SimdSearch`.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{Searcher, Strategy, SearchIn, CountIn};
+//! let needle = Searcher::new(b"Rabbit");
+//! assert_eq!(Strategy::HorspoolMemchr, needle.strategy());
+//! assert_eq!(2, needle.count_in(b"the White Rabbit and the March Hare and the Rabbit"));
+//! ```
+use boyer_moore::{BoyerMoore, BoyerMooreIter};
+use horspool_memchr::{HorspoolMemchr, HorspoolMemchrIter};
+use memchr_search::{MemchrSearch, MemchrSearchIter};
+use simd_search::{SimdSearch, SimdSearchIter};
+use rare_bytes::RareBytes;
+use skip_search::get_period;
+use super::{SearchIn, MatchIn};
+
+// Bytes which are less common than this, such as most capital letters and punctuation, are rare
+// enough for the memchr prefilter to beat testing every position
+const RARE_BYTE_FREQUENCY: u8 = 210;
+
+/// The search algorithm chosen by a `Searcher`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// `memchr` for the first byte, then compare the rest of the needle.
+    Memchr,
+    /// `BoyerMoore`, for periodic needles.
+    BoyerMoore,
+    /// Boyer-Moore-Horspool with a `memchr` prefilter on the rarest bytes of the needle.
+    HorspoolMemchr,
+    /// `SimdSearch`.
+    Simd,
+}
+
+// the skip searches hold their tables inline, so they are boxed to keep the others small
+enum Implementation <'a> {
+    Memchr(MemchrSearch<'a>),
+    BoyerMoore(Box<BoyerMoore<'a, u8>>),
+    HorspoolMemchr(Box<HorspoolMemchr<'a>>),
+    Simd(SimdSearch<'a>),
+}
+
+pub struct Searcher <'a> {
+    needle: &'a [u8],
+    implementation: Implementation<'a>,
+}
+
+impl <'a> Searcher <'a> {
+    /// Construct a new search object, choosing the algorithm from the length, periodicity and
+    /// bytes of the needle.
+    pub fn new(needle: &'a [u8]) -> Searcher<'a> {
+        let len = needle.len();
+        let implementation = if len <= 1 {
+            Implementation::Memchr(MemchrSearch::new(needle))
+        } else if RareBytes::new(needle).rarest_frequency() < RARE_BYTE_FREQUENCY {
+            Implementation::HorspoolMemchr(Box::new(HorspoolMemchr::new(needle)))
+        } else if len > 2 && get_period(needle) * 2 <= len {
+            Implementation::BoyerMoore(Box::new(BoyerMoore::new(needle)))
+        } else {
+            Implementation::Simd(SimdSearch::new(needle))
+        };
+        Searcher { needle, implementation }
+    }
+
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[u8] {
+        self.needle
+    }
+
+    /// The algorithm that was chosen for the needle.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Searcher, Strategy};
+    /// assert_eq!(Strategy::Memchr, Searcher::new(b"x").strategy());
+    /// assert_eq!(Strategy::BoyerMoore, Searcher::new(b"abcabcabc").strategy());
+    /// assert_eq!(Strategy::Simd, Searcher::new(b"thoughtfully").strategy());
+    /// ```
+    pub fn strategy(&self) -> Strategy {
+        match self.implementation {
+            Implementation::Memchr(_) => Strategy::Memchr,
+            Implementation::BoyerMoore(_) => Strategy::BoyerMoore,
+            Implementation::HorspoolMemchr(_) => Strategy::HorspoolMemchr,
+            Implementation::Simd(_) => Strategy::Simd,
+        }
+    }
+
+    fn iter<'b>(&'b self, haystack: &'b [u8], overlapping_matches: bool) -> SearcherIter<'b> {
+        macro_rules! iter {
+            ($searcher:expr, $variant:ident) => {
+                if overlapping_matches {
+                    ImplementationIter::$variant($searcher.find_overlapping_in(haystack))
+                } else {
+                    ImplementationIter::$variant($searcher.find_in(haystack))
+                }
+            }
+        }
+        let inner = match self.implementation {
+            Implementation::Memchr(ref searcher) => iter!(searcher, Memchr),
+            Implementation::BoyerMoore(ref searcher) => iter!(searcher, BoyerMoore),
+            Implementation::HorspoolMemchr(ref searcher) => iter!(searcher, HorspoolMemchr),
+            Implementation::Simd(ref searcher) => iter!(searcher, Simd),
+        };
+        SearcherIter { inner }
+    }
+}
+
+
+impl <'a> SearchIn<[u8]> for Searcher<'a> {
+    type Iter<'b> = SearcherIter<'b> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches.
+    fn find_in<'b>(&'b self, haystack: &'b [u8]) -> SearcherIter<'b> {
+        self.iter(haystack, false)
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{Searcher, SearchIn};
+    /// let needle = Searcher::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [u8]) -> SearcherIter<'b> {
+        self.iter(haystack, true)
+    }
+}
+
+impl <'a> MatchIn<u8> for Searcher<'a> {
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
+
+enum ImplementationIter <'a> {
+    Memchr(MemchrSearchIter<'a>),
+    BoyerMoore(BoyerMooreIter<'a, u8>),
+    HorspoolMemchr(HorspoolMemchrIter<'a>),
+    Simd(SimdSearchIter<'a>),
+}
+
+pub struct SearcherIter <'a> {
+    inner: ImplementationIter<'a>,
+}
+
+impl <'a> Iterator for SearcherIter<'a> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        match self.inner {
+            ImplementationIter::Memchr(ref mut iter) => iter.next(),
+            ImplementationIter::BoyerMoore(ref mut iter) => iter.next(),
+            ImplementationIter::HorspoolMemchr(ref mut iter) => iter.next(),
+            ImplementationIter::Simd(ref mut iter) => iter.next(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+
+    #[test]
+    pub fn test_strategies() {
+        assert_eq!(Strategy::Memchr, Searcher::new(b"").strategy());
+        assert_eq!(Strategy::Memchr, Searcher::new(b"e").strategy());
+        assert_eq!(Strategy::BoyerMoore, Searcher::new(b"aaaaaaaa").strategy());
+        assert_eq!(Strategy::HorspoolMemchr, Searcher::new(b"box").strategy());
+        assert_eq!(Strategy::Simd, Searcher::new(b"985750").strategy());
+        assert_eq!(Strategy::Simd, Searcher::new(b"abaa").strategy());
+    }
+
+    #[test]
+    pub fn test_every_strategy_searches() {
+        let haystack = b"the box of 985750 aaaaaaaaa, and e";
+        for (needle, found, overlapping) in [
+            (&b"e"[..], vec![2, 33], vec![2, 33]),
+            (b"aaaa", vec![18, 22], vec![18, 19, 20, 21, 22, 23]),
+            (b"box", vec![4], vec![4]),
+            (b"985750", vec![11], vec![11]),
+            (b"", (0 ..= haystack.len()).collect(), (0 ..= haystack.len()).collect()),
+        ] {
+            let searcher = Searcher::new(needle);
+            assert_eq!(found, searcher.find_in(haystack).collect::<Vec<usize>>(), "{:?}", searcher.strategy());
+            assert_eq!(overlapping, searcher.find_overlapping_in(haystack).collect::<Vec<usize>>(), "{:?}", searcher.strategy());
+            assert_eq!(found.len(), searcher.count_in(haystack));
+        }
+    }
+}
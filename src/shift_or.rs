@@ -0,0 +1,210 @@
+//! Implementation of the Shift-Or (bitap) search algorithm of Baeza-Yates and Gonnet. The state is
+//! a single word, in which bit `i` is clear when the first `i + 1` symbols of the needle match the
+//! haystack just before the current position. Each symbol of the haystack costs a shift, an OR
+//! and a test, without any data-dependent branches, so it is fast and predictable for short
+//! needles. Needles are limited to 64 symbols, the width of the state.
+//!
+//! The table holds a mask for every symbol up to the largest one in the needle, so this is
+//! intended for bytes and other small alphabets.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{ShiftOr, SearchIn};
+//! let needle = ShiftOr::new(b"fn");
+//! let haystack = b"pub fn main() { fn inner() {} }";
+//! assert_eq!(vec![4, 16], needle.find_in(haystack).collect::<Vec<usize>>());
+//! ```
+use error::NeedleError;
+use super::{SearchIn, MatchIn};
+
+/// The longest needle that `ShiftOr` can search for.
+pub const SHIFT_OR_MAX_LEN: usize = 64;
+
+pub struct ShiftOr <'a, T: 'a> {
+    needle: &'a [T],
+    // for each symbol, the positions in the needle where it occurs are the clear bits
+    masks: Vec<u64>,
+}
+
+impl <'a, T> ShiftOr<'a, T>
+    where T: Copy + Into<usize>
+{
+    /// Construct a new Shift-Or search object, and pre-compute the symbol masks.
+    ///
+    /// # Panics
+    /// If the needle is longer than 64 symbols. Use `try_new` to handle this instead.
+    pub fn new(needle: &'a [T]) -> ShiftOr<'a, T> {
+        match ShiftOr::try_new(needle) {
+            Ok(searcher) => searcher,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Construct a new Shift-Or search object, or return an error if the needle is longer than
+    /// 64 symbols. Longer needles are better served by `BoyerMoore` or `TwoWay`.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{ShiftOr, NeedleError};
+    /// assert!(ShiftOr::try_new(&[b'a'; 64]).is_ok());
+    /// assert_eq!(Some(NeedleError::TooLong(65)), ShiftOr::try_new(&[b'a'; 65]).err());
+    /// ```
+    pub fn try_new(needle: &'a [T]) -> Result<ShiftOr<'a, T>, NeedleError> {
+        if needle.len() > SHIFT_OR_MAX_LEN {
+            return Err(NeedleError::TooLong(needle.len()));
+        }
+        let symbols = needle.iter().map(|&c| c.into() + 1).max().unwrap_or(0);
+        let mut masks = vec![!0; symbols];
+        for (i, &c) in needle.iter().enumerate() {
+            masks[c.into()] &= !(1 << i);
+        }
+        Ok(ShiftOr { needle, masks })
+    }
+
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[T] {
+        self.needle
+    }
+
+    // Find the end of the first match at or after `position`, continuing from `state`
+    fn find_from_position(&self, haystack: &[T], mut position: usize, state: &mut u64) -> Option<usize> {
+        let found = 1 << (self.needle.len() - 1);
+        while position < haystack.len() {
+            let mask = self.masks.get(haystack[position].into()).cloned().unwrap_or(!0);
+            *state = (*state << 1) | mask;
+            position += 1;
+            if *state & found == 0 {
+                return Some(position);
+            }
+        }
+        None
+    }
+}
+
+
+impl <'a, T> SearchIn<[T]> for ShiftOr<'a, T>
+    where T: Copy + Into<usize>
+{
+    type Iter<'b> = ShiftOrIter<'b, T> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches.
+    fn find_in<'b>(&'b self, haystack: &'b [T]) -> ShiftOrIter<'b, T> {
+        ShiftOrIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            state: !0,
+            overlapping_matches: false,
+        }
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{ShiftOr, SearchIn};
+    /// let needle = ShiftOr::new(b"aba");
+    /// assert_eq!(vec![0, 2, 4], needle.find_overlapping_in(b"abababa").collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> ShiftOrIter<'b, T> {
+        ShiftOrIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            state: !0,
+            overlapping_matches: true,
+        }
+    }
+}
+
+impl <'a, T> MatchIn<T> for ShiftOr<'a, T>
+    where T: Copy + Into<usize>
+{
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
+
+pub struct ShiftOrIter <'a, T: 'a> {
+    searcher: &'a ShiftOr<'a, T>,
+    haystack: &'a [T],
+    position: usize,
+    state: u64,
+    overlapping_matches: bool,
+}
+
+impl <'a, T> Iterator for ShiftOrIter<'a, T>
+    where T: Copy + Into<usize>
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        let len = self.searcher.needle.len();
+        if len == 0 {
+            // an empty needle matches at every position, including the end of the haystack
+            if self.position > self.haystack.len() {
+                return None;
+            }
+            self.position += 1;
+            return Some(self.position - 1);
+        }
+        self.searcher.find_from_position(self.haystack, self.position, &mut self.state)
+            .map(|end| {
+                // carry on scanning from the end of the match, as the state already covers
+                // any overlapping matches
+                self.position = end;
+                if !self.overlapping_matches {
+                    // forget the partial matches which started within this one
+                    self.state = !0;
+                }
+                end - len
+            })
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+
+    #[test]
+    pub fn test_simple() {
+        let needle = ShiftOr::new(b"ghi");
+        assert_eq!(Some(8), needle.find_first_in(b"abc def ghi jkl"));
+        assert_eq!(None, needle.find_first_in(b"gh"));
+    }
+
+    #[test]
+    pub fn test_overlapping() {
+        let needle = ShiftOr::new(b"aa");
+        assert_eq!(vec![0, 2], needle.find_in(b"aaaaa").collect::<Vec<usize>>());
+        assert_eq!(vec![0, 1, 2, 3], needle.find_overlapping_in(b"aaaaa").collect::<Vec<usize>>());
+        assert_eq!(2, needle.count_in(b"aaaaa"));
+    }
+
+    #[test]
+    pub fn test_longest_needle() {
+        let mut needle = vec![b'a'; 64];
+        needle[63] = b'b';
+        let mut haystack = vec![b'a'; 200];
+        haystack[150] = b'b';
+        assert_eq!(Some(87), ShiftOr::new(&needle).find_first_in(&haystack));
+        assert_eq!(Some(NeedleError::TooLong(65)), ShiftOr::try_new(&haystack[.. 65]).err());
+    }
+
+    #[test]
+    pub fn test_generic_alphabet() {
+        let code_units: Vec<u16> = "ŝearch ĥere".encode_utf16().collect();
+        assert_eq!(Some(7), ShiftOr::new(&code_units[7 ..]).find_first_in(&code_units));
+    }
+
+    #[test]
+    pub fn test_empty_needle() {
+        let needle = ShiftOr::new(b"");
+        assert_eq!(vec![0, 1, 2, 3], needle.find_in(b"abc").collect::<Vec<usize>>());
+    }
+}
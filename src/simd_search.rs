@@ -0,0 +1,299 @@
+//! A byte searcher which compares the first and last bytes of the needle against many positions
+//! of the haystack at once, and only compares the rest of the needle where both of them match.
+//! On x86_64 it uses AVX2 to test 32 positions at a time when the CPU supports it, and SSE2 to
+//! test 16 otherwise. The CPU is checked once, when the searcher is constructed. Other
+//! architectures use a portable fallback that tests one position at a time.
+//!
+//! Unlike the skip searches, this never skips ahead by more than a block, but it needs no tables
+//! and each block takes only a few instructions, so it is fast for short needles and for
+//! haystacks where the first and last bytes of the needle are rare.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{SimdSearch, SearchIn};
+//! let needle = SimdSearch::new(b"example");
+//! let haystack = b"This is an example of searching for a word";
+//! assert_eq!(Some(11), needle.find_first_in(haystack));
+//! ```
+use skip_search::position_after_match;
+use super::{SearchIn, MatchIn};
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+// The instruction set that the search uses
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Isa {
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    Portable,
+}
+
+impl Isa {
+    #[cfg(target_arch = "x86_64")]
+    fn detect() -> Isa {
+        if is_x86_feature_detected!("avx2") {
+            Isa::Avx2
+        } else {
+            // SSE2 is part of the x86_64 baseline
+            Isa::Sse2
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    fn detect() -> Isa {
+        Isa::Portable
+    }
+}
+
+pub struct SimdSearch <'a> {
+    needle: &'a [u8],
+    isa: Isa,
+}
+
+impl <'a> SimdSearch <'a> {
+    /// Construct a new search object, choosing the fastest implementation that the CPU supports.
+    pub fn new(needle: &'a [u8]) -> SimdSearch<'a> {
+        SimdSearch { needle, isa: Isa::detect() }
+    }
+
+    /// Construct a new search object which uses the portable implementation, whatever the CPU
+    /// supports. This is mostly useful for comparing against the vectorised implementations.
+    pub fn portable(needle: &'a [u8]) -> SimdSearch<'a> {
+        SimdSearch { needle, isa: Isa::Portable }
+    }
+
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[u8] {
+        self.needle
+    }
+
+    /// The name of the implementation in use: `"avx2"`, `"sse2"` or `"portable"`.
+    pub fn implementation(&self) -> &'static str {
+        match self.isa {
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => "avx2",
+            #[cfg(target_arch = "x86_64")]
+            Isa::Sse2 => "sse2",
+            Isa::Portable => "portable",
+        }
+    }
+
+    fn find_from_position(&self, haystack: &[u8], position: usize) -> Option<usize> {
+        let len = self.needle.len();
+        if len == 0 {
+            return if position <= haystack.len() { Some(position) } else { None };
+        }
+        if haystack.len() < len {
+            return None;
+        }
+        match self.isa {
+            // the detected features are present, and the loads stay within the haystack
+            #[cfg(target_arch = "x86_64")]
+            Isa::Avx2 => unsafe { find_avx2(self.needle, haystack, position) },
+            #[cfg(target_arch = "x86_64")]
+            Isa::Sse2 => unsafe { find_sse2(self.needle, haystack, position) },
+            Isa::Portable => find_portable(self.needle, haystack, position),
+        }
+    }
+}
+
+// Whether the needle matches at `position`, given that its first and last bytes do
+#[inline(always)]
+fn matches_between(needle: &[u8], haystack: &[u8], position: usize) -> bool {
+    let len = needle.len();
+    len < 3 || needle[1 .. len - 1] == haystack[position + 1 .. position + len - 1]
+}
+
+fn find_portable(needle: &[u8], haystack: &[u8], position: usize) -> Option<usize> {
+    let len = needle.len();
+    let (first, last) = (needle[0], needle[len - 1]);
+    (position ..= haystack.len() - len).find(|&p| {
+        haystack[p] == first && haystack[p + len - 1] == last && matches_between(needle, haystack, p)
+    })
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn find_avx2(needle: &[u8], haystack: &[u8], mut position: usize) -> Option<usize> {
+    const BLOCK: usize = 32;
+    let len = needle.len();
+    let first = _mm256_set1_epi8(needle[0] as i8);
+    let last = _mm256_set1_epi8(needle[len - 1] as i8);
+    let start = haystack.as_ptr();
+    while position + len - 1 + BLOCK <= haystack.len() {
+        let a = _mm256_loadu_si256(start.add(position) as *const __m256i);
+        let b = _mm256_loadu_si256(start.add(position + len - 1) as *const __m256i);
+        let both = _mm256_and_si256(_mm256_cmpeq_epi8(a, first), _mm256_cmpeq_epi8(b, last));
+        let mut candidates = _mm256_movemask_epi8(both) as u32;
+        while candidates != 0 {
+            let candidate = position + candidates.trailing_zeros() as usize;
+            if matches_between(needle, haystack, candidate) {
+                return Some(candidate);
+            }
+            candidates &= candidates - 1;
+        }
+        position += BLOCK;
+    }
+    if position + len <= haystack.len() {
+        find_portable(needle, haystack, position)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn find_sse2(needle: &[u8], haystack: &[u8], mut position: usize) -> Option<usize> {
+    const BLOCK: usize = 16;
+    let len = needle.len();
+    let first = _mm_set1_epi8(needle[0] as i8);
+    let last = _mm_set1_epi8(needle[len - 1] as i8);
+    let start = haystack.as_ptr();
+    while position + len - 1 + BLOCK <= haystack.len() {
+        let a = _mm_loadu_si128(start.add(position) as *const __m128i);
+        let b = _mm_loadu_si128(start.add(position + len - 1) as *const __m128i);
+        let both = _mm_and_si128(_mm_cmpeq_epi8(a, first), _mm_cmpeq_epi8(b, last));
+        let mut candidates = _mm_movemask_epi8(both) as u32;
+        while candidates != 0 {
+            let candidate = position + candidates.trailing_zeros() as usize;
+            if matches_between(needle, haystack, candidate) {
+                return Some(candidate);
+            }
+            candidates &= candidates - 1;
+        }
+        position += BLOCK;
+    }
+    if position + len <= haystack.len() {
+        find_portable(needle, haystack, position)
+    } else {
+        None
+    }
+}
+
+
+impl <'a> SearchIn<[u8]> for SimdSearch<'a> {
+    type Iter<'b> = SimdSearchIter<'b> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches.
+    fn find_in<'b>(&'b self, haystack: &'b [u8]) -> SimdSearchIter<'b> {
+        SimdSearchIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            overlapping_matches: false,
+        }
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{SimdSearch, SearchIn};
+    /// let needle = SimdSearch::new(b"aba");
+    /// assert_eq!(vec![0, 2, 4], needle.find_overlapping_in(b"abababa").collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [u8]) -> SimdSearchIter<'b> {
+        SimdSearchIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            overlapping_matches: true,
+        }
+    }
+}
+
+impl <'a> MatchIn<u8> for SimdSearch<'a> {
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
+
+pub struct SimdSearchIter <'a> {
+    searcher: &'a SimdSearch<'a>,
+    haystack: &'a [u8],
+    position: usize,
+    overlapping_matches: bool,
+}
+
+impl <'a> Iterator for SimdSearchIter<'a> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        self.searcher.find_from_position(self.haystack, self.position)
+            .inspect(|&position| {
+                self.position = position_after_match(position, self.searcher.needle.len(), self.overlapping_matches);
+            })
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+    use test_util::{Random, naive_find};
+
+    fn all_implementations(needle: &[u8]) -> Vec<SimdSearch<'_>> {
+        let mut searchers = vec![SimdSearch::portable(needle)];
+        #[cfg(target_arch = "x86_64")]
+        {
+            searchers.push(SimdSearch { needle, isa: Isa::Sse2 });
+            if is_x86_feature_detected!("avx2") {
+                searchers.push(SimdSearch { needle, isa: Isa::Avx2 });
+            }
+        }
+        searchers
+    }
+
+    #[test]
+    pub fn test_simple() {
+        for needle in all_implementations(b"ghi") {
+            assert_eq!(Some(8), needle.find_first_in(b"abc def ghi jkl"), "{}", needle.implementation());
+        }
+    }
+
+    #[test]
+    pub fn test_block_boundaries() {
+        let mut haystack = vec![b'.'; 100];
+        haystack[31 .. 34].copy_from_slice(b"xyz");
+        haystack[97 .. 100].copy_from_slice(b"xyz");
+        for needle in all_implementations(b"xyz") {
+            assert_eq!(vec![31, 97], needle.find_in(&haystack).collect::<Vec<usize>>(), "{}", needle.implementation());
+            assert_eq!(2, needle.count_in(&haystack));
+        }
+    }
+
+    #[test]
+    pub fn test_short_needles() {
+        for needle in all_implementations(b"a") {
+            assert_eq!(vec![0, 1, 2], needle.find_in(b"aaa").collect::<Vec<usize>>());
+        }
+        for needle in all_implementations(b"") {
+            assert_eq!(vec![0, 1, 2, 3], needle.find_in(b"abc").collect::<Vec<usize>>());
+        }
+        for needle in all_implementations(b"abcd") {
+            assert_eq!(None, needle.find_first_in(b"abc"));
+        }
+    }
+
+    #[test]
+    pub fn test_agrees_with_naive() {
+        let mut random = Random::new(3);
+        let haystack: Vec<u8> = (0 .. 3000).map(|_| b'a' + random.below(2) as u8).collect();
+        for _ in 0 .. 100 {
+            let len = 1 + random.below(40) as usize;
+            let needle: Vec<u8> = (0 .. len).map(|_| b'a' + random.below(2) as u8).collect();
+            let end = random.below(3000) as usize;
+            let haystack = &haystack[.. end];
+            let expected = naive_find(haystack, len, |window| window == &needle[..]);
+            for searcher in all_implementations(&needle) {
+                assert_eq!(expected, searcher.find_overlapping_in(haystack).collect::<Vec<usize>>(), "{}", searcher.implementation());
+            }
+        }
+    }
+}
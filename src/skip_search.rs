@@ -1,4 +1,8 @@
 //! The skip-search logic for Boyer-Moore algorithm
+use bad_chars::BadCharsTable;
+use error::NeedleError;
+use std::ops::Range;
+
 pub trait SkipSearch<T> {
     /// Given `bad_char`, a character from haystack that didn't match with the character in the needle at 
     /// `needle_position`, calculate how many characters can be skipped 
@@ -7,20 +11,41 @@ pub trait SkipSearch<T> {
     /// The number of characters in the needle
     fn len(&self) -> usize;
 
-    /// Retrieve a character from the index within needle
-    fn char_at(&self, index: usize) -> T;
+    /// Whether `c`, a character from the haystack, matches the character at index within needle
+    fn matches_at(&self, index: usize, c: T) -> bool;
+}
+
+/// The mirror image of `SkipSearch`, for searching from right to left
+pub trait ReverseSkipSearch<T>: SkipSearch<T> {
+    /// Given `bad_char`, the first (leftmost) character of the haystack window, and `needle_position`, the 
+    /// position in the needle of the character that didn't match when comparing from left to right, calculate
+    /// how many characters the window can be moved to the left
+    fn rev_skip_offset(&self, bad_char: T, needle_position: usize, haystack: &[T], haystack_position: usize) -> usize;
 }
 
 /// Find needle in haystack, starting at position within haystack
-pub fn find_from_position<'a, T, N>(needle: &'a N, haystack: &'a [T], mut position: usize) -> Option<usize>
+pub fn find_from_position<'a, T, N>(needle: &'a N, haystack: &'a [T], position: usize) -> Option<usize>
     where T: PartialEq + Into<usize> + Copy, 
           N: SkipSearch<T>
 {
-    let max_position = haystack.len() - needle.len(); 
+    find_from_position_with_memory(needle, haystack, position, 0)
+}
+
+/// Find needle in haystack, starting at position within haystack, when the first `memory` characters of
+/// the needle are already known to match at that position. They are not compared again (the Galil rule).
+pub fn find_from_position_with_memory<'a, T, N>(needle: &'a N, haystack: &'a [T], mut position: usize, mut memory: usize) -> Option<usize>
+    where T: PartialEq + Into<usize> + Copy, 
+          N: SkipSearch<T>
+{
+    if needle.len() == 0 {
+        // an empty needle matches at every position, including the end of the haystack
+        return if position <= haystack.len() { Some(position) } else { None };
+    }
+    let max_position = haystack.len().checked_sub(needle.len())?;
     while position <= max_position {
         let mut needle_position = needle.len() - 1;
-        while haystack[position + needle_position] == needle.char_at(needle_position) {
-            if needle_position == 0 {
+        while needle.matches_at(needle_position, haystack[position + needle_position]) {
+            if needle_position == memory {
                 return Some(position);
             } else {
                 needle_position -= 1;
@@ -28,20 +53,163 @@ pub fn find_from_position<'a, T, N>(needle: &'a N, haystack: &'a [T], mut positi
         }
         let bad_char = haystack[position + needle.len() - 1];
         position += needle.skip_offset(bad_char, needle_position, haystack, position);
+        memory = 0;
     }
     None
 }
 
 
+/// Find the last occurence of needle in haystack which finishes before the position `end` within haystack
+pub fn rfind_from_position<'a, T, N>(needle: &'a N, haystack: &'a [T], end: usize) -> Option<usize>
+    where T: PartialEq + Into<usize> + Copy, 
+          N: ReverseSkipSearch<T>
+{
+    rfind_from_position_with_memory(needle, haystack, end, 0)
+}
+
+/// The mirror image of `find_from_position_with_memory`: the last `memory` characters of the needle are
+/// already known to match when it finishes at `end`.
+pub fn rfind_from_position_with_memory<'a, T, N>(needle: &'a N, haystack: &'a [T], end: usize, mut memory: usize) -> Option<usize>
+    where T: PartialEq + Into<usize> + Copy, 
+          N: ReverseSkipSearch<T>
+{
+    if end < needle.len() {
+        return None;
+    } else if needle.len() == 0 {
+        return Some(end);
+    }
+    let mut position = end - needle.len();
+    loop {
+        let mut needle_position = 0;
+        while needle.matches_at(needle_position, haystack[position + needle_position]) {
+            if needle_position == needle.len() - 1 - memory {
+                return Some(position);
+            } else {
+                needle_position += 1;
+            }
+        }
+        let bad_char = haystack[position];
+        let skip = needle.rev_skip_offset(bad_char, needle_position, haystack, position);
+        if skip > position {
+            return None;
+        }
+        position -= skip;
+        memory = 0;
+    }
+}
+
+
+/// The smallest period of the needle: the smallest shift which lines the needle up with itself.
+pub fn get_period<T: PartialEq>(needle: &[T]) -> usize {
+    let len = needle.len();
+    let suffixes = get_suffix_table(needle);
+    // the longest prefix which is also a suffix gives the smallest period
+    (0 .. len.saturating_sub(1)).rev()
+        .find(|&i| suffixes[i] == i + 1)
+        .map_or(len, |i| len - i - 1)
+}
+
+
+/// Check that a needle can be searched for with a bad character table of type `B`, for the fallible constructors
+pub fn check_needle<T, B>(needle: &[T]) -> Result<(), NeedleError>
+    where T: Into<usize> + Copy,
+          B: BadCharsTable
+{
+    if needle.is_empty() {
+        return Err(NeedleError::EmptyNeedle);
+    }
+    match needle.iter().map(|&c| c.into()).find(|&c| !B::supports(c)) {
+        Some(c) => Err(NeedleError::UnsupportedSymbol(c)),
+        None => Ok(()),
+    }
+}
+
+/// Check that a range to search in lies within the haystack, as slicing the haystack would
+pub fn check_range(range: &Range<usize>, haystack_len: usize) {
+    assert!(range.start <= range.end && range.end <= haystack_len,
+        "range {:?} is not within a haystack of length {}", range, haystack_len);
+}
+
+/// The position to search from after finding a match at `position`. An empty needle matches at every
+/// position, so the search always moves forward by at least one.
+#[inline]
+pub fn position_after_match(position: usize, needle_len: usize, overlapping_matches: bool) -> usize {
+    if overlapping_matches || needle_len == 0 {
+        position + 1
+    } else {
+        position + needle_len
+    }
+}
+
+/// Map the ASCII upper case letters to lower case, leaving every other symbol unchanged
+#[inline]
+pub fn fold_ascii_case(c: usize) -> usize {
+    if c >= b'A' as usize && c <= b'Z' as usize {
+        c + (b'a' - b'A') as usize
+    } else {
+        c
+    }
+}
+
+/// Compare two symbols, treating ASCII upper and lower case letters as equal if `ascii_case_insensitive` is set
+#[inline]
+pub fn symbols_match<T: PartialEq + Into<usize> + Copy>(a: T, b: T, ascii_case_insensitive: bool) -> bool {
+    a == b || ascii_case_insensitive && fold_ascii_case(a.into()) == fold_ascii_case(b.into())
+}
+
+// Sets the skip for a symbol and, if `ascii_case_insensitive` is set, for the other case of an ASCII letter too
+fn set_bad_char<B: BadCharsTable>(table: &mut B, c: usize, skip: usize, ascii_case_insensitive: bool) {
+    table.set(c, skip);
+    if ascii_case_insensitive {
+        let lower = fold_ascii_case(c);
+        if lower != c {
+            table.set(lower, skip);
+        } else if lower >= b'a' as usize && lower <= b'z' as usize {
+            table.set(lower - (b'a' - b'A') as usize, skip);
+        }
+    }
+}
+
 // Bad characters table is used for when the last (rightmost) character of the needle doesn't match. The table
 // gives the number of elements to skip, to find a character that does match.
-pub fn build_bad_chars_table<T>(needle: &[T]) -> [usize; 256] 
-    where T: Into<usize> + Copy 
+pub fn build_bad_chars_table<T, B>(needle: &[T], ascii_case_insensitive: bool) -> B 
+    where T: Into<usize> + Copy,
+          B: BadCharsTable
+{
+    let mut table = B::new(needle.len());
+    for i in 0 .. needle.len().saturating_sub(1) {
+        set_bad_char(&mut table, needle[i].into(), needle.len() - i - 1, ascii_case_insensitive);
+    }
+    table
+}
+
+/// The bad character table for a needle in which `None` is a wildcard that matches any symbol. A wildcard
+/// matches whichever bad character is found, so no skip may move the window past the last wildcard before
+/// the final position of the needle.
+pub fn build_masked_bad_chars_table<T, B>(needle: &[Option<T>]) -> B
+    where T: Into<usize> + Copy,
+          B: BadCharsTable
+{
+    let last = needle.len().saturating_sub(1);
+    let start = needle[.. last].iter().rposition(Option::is_none).map_or(0, |i| i + 1);
+    let mut table = B::new(needle.len() - start);
+    for (i, c) in needle.iter().enumerate().take(last).skip(start) {
+        if let Some(c) = *c {
+            table.set(c.into(), needle.len() - i - 1);
+        }
+    }
+    table
+}
+
+// The mirror image of the bad characters table, for when the first (leftmost) character of the needle 
+// doesn't match while searching from right to left.
+pub fn build_rev_bad_chars_table<T, B>(needle: &[T], ascii_case_insensitive: bool) -> B 
+    where T: Into<usize> + Copy,
+          B: BadCharsTable
 {
-    let mut table = [needle.len(); 256];
-    for i in 0 .. needle.len() - 1 {
-        let c: usize = needle[i].into();
-        table[c] = needle.len() - i - 1;
+    let mut table = B::new(needle.len());
+    for i in (1 .. needle.len()).rev() {
+        set_bad_char(&mut table, needle[i].into(), i, ascii_case_insensitive);
     }
     table
 }
@@ -78,10 +246,25 @@ pub fn get_suffix_table<T: PartialEq>(needle: &[T]) -> Vec<usize> {
 // When a suffix of the needle matches, but fails at the next character, this table gives the number of 
 // elements to skip, to find another subsequence that matches the suffix but with a different preceding character.
 pub fn build_good_suffixes_table<T: PartialEq>(needle: &[T]) -> Vec<usize> {
-    let suffixes = get_suffix_table(&needle);
     let len = needle.len();
+    if len == 0 {
+        return Vec::new();
+    }
+    let suffixes = get_suffix_table(needle);
     let mut table = vec![len - 1; len];
 
+    // A prefix of the needle which is also a suffix of it can be moved up to where the suffix was
+    // matched, as long as the part of the needle that has matched is at least as long as the prefix
+    let mut needle_index = 0;
+    for i in (0 .. len - 1).rev() {
+        if suffixes[i] == i + 1 {
+            while needle_index < len - 1 - i {
+                table[needle_index] = len - 1 - i;
+                needle_index += 1;
+            }
+        }
+    }
+
     for (i, suffix_len) in suffixes.into_iter().enumerate() {
         let needle_index = len - suffix_len - 1;
         let skip = len - i - 1;
@@ -93,29 +276,109 @@ pub fn build_good_suffixes_table<T: PartialEq>(needle: &[T]) -> Vec<usize> {
     table
 }
 
+// The mirror image of the good suffixes table: when a prefix of the needle matches, but fails at the next 
+// character, this table gives the number of elements to skip to the left. It is indexed by needle position, 
+// in the same way as the good suffixes table.
+pub fn build_rev_good_suffixes_table<T: PartialEq + Clone>(needle: &[T]) -> Vec<usize> {
+    let reversed: Vec<T> = needle.iter().rev().cloned().collect();
+    let mut table = build_good_suffixes_table(&reversed);
+    table.reverse();
+    table
+}
+
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use bad_chars::DenseTable;
 
     #[test]
     pub fn test_good_suffix_table2() {
         let needle = "GCAGAGAG".as_bytes();
-        let table = build_good_suffixes_table(&needle);
+        let table = build_good_suffixes_table(needle);
         assert_eq!(vec![7,7,7,2,7,4,7,1], table);
     }
 
     #[test]
     pub fn test_suffix_table() {
         let needle = "abcbabcabab".as_bytes();
-        let table = get_suffix_table(&needle);
+        let table = get_suffix_table(needle);
         assert_eq!(vec![0,2,0,1,0,3,0,0,2,0,0], table);
     }
 
     #[test]
     pub fn test_good_suffix_table() {
         let needle = "abcbabcabab".as_bytes();
-        let table = build_good_suffixes_table(&needle);
-        assert_eq!(vec![10,10,10,10,10,10,10,5,2,7,1], table);
+        let table = build_good_suffixes_table(needle);
+        assert_eq!(vec![9,9,9,9,9,9,9,5,2,7,1], table);
+    }
+
+    #[test]
+    pub fn test_rev_bad_chars_table() {
+        let needle = "abcab".as_bytes();
+        let table: DenseTable = build_rev_bad_chars_table(needle, false);
+        assert_eq!(3, table.get(b'a' as usize));
+        assert_eq!(1, table.get(b'b' as usize));
+        assert_eq!(2, table.get(b'c' as usize));
+        assert_eq!(5, table.get(b'd' as usize));
+    }
+
+    #[test]
+    pub fn test_masked_bad_chars_table() {
+        let needle = [Some(b'a'), None, Some(b'b'), Some(b'c'), Some(b'd')];
+        let table: DenseTable = build_masked_bad_chars_table(&needle);
+        assert_eq!(3, table.get(b'a' as usize));
+        assert_eq!(2, table.get(b'b' as usize));
+        assert_eq!(1, table.get(b'c' as usize));
+        assert_eq!(3, table.get(b'd' as usize));
+        assert_eq!(3, table.get(b'x' as usize));
+        let table: DenseTable = build_masked_bad_chars_table(&[Some(b'a'), Some(b'b'), None]);
+        assert_eq!(2, table.get(b'a' as usize));
+        assert_eq!(3, table.get(b'x' as usize));
+    }
+
+    #[test]
+    pub fn test_ascii_case_insensitive_bad_chars_table() {
+        let needle = "aB1b".as_bytes();
+        let table: DenseTable = build_bad_chars_table(needle, true);
+        assert_eq!(3, table.get(b'a' as usize));
+        assert_eq!(3, table.get(b'A' as usize));
+        assert_eq!(2, table.get(b'b' as usize));
+        assert_eq!(2, table.get(b'B' as usize));
+        assert_eq!(1, table.get(b'1' as usize));
+        assert_eq!(4, table.get(b'c' as usize));
+    }
+
+    #[test]
+    pub fn test_good_suffix_table_with_border() {
+        // after matching "abab", the prefix "bab" can be lined up with the end of the match
+        let needle = "babab".as_bytes();
+        let table = build_good_suffixes_table(needle);
+        assert_eq!(vec![2,2,4,4,1], table);
+    }
+
+    #[test]
+    pub fn test_empty_needle_tables() {
+        let table: DenseTable = build_bad_chars_table(b"", false);
+        assert_eq!(0, table.get(b'a' as usize));
+        let table: DenseTable = build_rev_bad_chars_table(b"", false);
+        assert_eq!(0, table.get(b'a' as usize));
+        assert!(build_good_suffixes_table(b"").is_empty());
+        assert!(build_rev_good_suffixes_table(b"").is_empty());
+    }
+
+    #[test]
+    pub fn test_period() {
+        assert_eq!(3, get_period(b"abaabaa"));
+        assert_eq!(1, get_period(b"aaaa"));
+        assert_eq!(5, get_period(b"abcde"));
+        assert_eq!(0, get_period(b""));
+    }
+
+    #[test]
+    pub fn test_rev_good_suffix_table() {
+        let needle = "GAGAGACG".as_bytes();
+        let table = build_rev_good_suffixes_table(needle);
+        assert_eq!(vec![1,7,4,7,2,7,7,7], table);
     }
 }
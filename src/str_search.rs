@@ -0,0 +1,179 @@
+//! Searching in `str`. A `StrSearch` wraps a byte searcher whose needle came from a `&str`. Because
+//! UTF-8 is self-synchronizing, a valid UTF-8 needle can only match a valid UTF-8 haystack at char
+//! boundaries, so every offset that is found is a valid index into the haystack. An empty needle
+//! matches at every char boundary, as it does with `str::find`.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{BoyerMoore, SearchIn};
+//! let needle = BoyerMoore::new_str("größer");
+//! let haystack = "viel größer als größer";
+//! assert_eq!(vec![5,18], needle.find_in(haystack).collect::<Vec<usize>>());
+//! assert_eq!(Some("größer"), needle.matches_in(haystack).next());
+//! ```
+use super::{SearchIn, ReverseSearchIn};
+
+pub struct StrSearch <S> {
+    searcher: S,
+    len: usize,
+}
+
+impl <S> StrSearch<S> {
+    // `searcher` must search for the UTF-8 bytes of a `str`, whose length is `len`
+    pub(crate) fn new(searcher: S, len: usize) -> StrSearch<S> {
+        StrSearch { searcher, len }
+    }
+
+    // The length of the needle, in bytes
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The underlying byte searcher.
+    pub fn as_bytes(&self) -> &S {
+        &self.searcher
+    }
+}
+
+impl <S> StrSearch<S>
+    where S: SearchIn<[u8]>
+{
+    /// Returns an iterator over the sub-slices of the haystack that match the needle. Like
+    /// `find_in`, this will not find overlapping matches.
+    pub fn matches_in<'a>(&'a self, haystack: &'a str) -> StrMatches<'a, StrSearchIter<'a, S::Iter<'a>>> {
+        StrMatches {
+            haystack,
+            len: self.len(),
+            offsets: self.find_in(haystack),
+        }
+    }
+}
+
+impl <S> SearchIn<str> for StrSearch<S>
+    where S: SearchIn<[u8]>
+{
+    type Iter<'a> = StrSearchIter<'a, S::Iter<'a>> where Self: 'a;
+
+    /// Returns an iterator that will produce the byte offsets of the needle in the haystack.
+    /// This iterator will not find overlapping matches.
+    fn find_in<'a>(&'a self, haystack: &'a str) -> StrSearchIter<'a, S::Iter<'a>> {
+        StrSearchIter { haystack, offsets: self.searcher.find_in(haystack.as_bytes()) }
+    }
+
+    /// Returns an iterator that will produce the byte offsets of the needle in the haystack.
+    /// This iterator will find overlapping matches.
+    fn find_overlapping_in<'a>(&'a self, haystack: &'a str) -> StrSearchIter<'a, S::Iter<'a>> {
+        StrSearchIter { haystack, offsets: self.searcher.find_overlapping_in(haystack.as_bytes()) }
+    }
+}
+
+impl <S> ReverseSearchIn<str> for StrSearch<S>
+    where S: ReverseSearchIn<[u8]>
+{
+    type RevIter<'a> = StrSearchIter<'a, S::RevIter<'a>> where Self: 'a;
+
+    fn rfind_in<'a>(&'a self, haystack: &'a str) -> StrSearchIter<'a, S::RevIter<'a>> {
+        StrSearchIter { haystack, offsets: self.searcher.rfind_in(haystack.as_bytes()) }
+    }
+
+    fn rfind_overlapping_in<'a>(&'a self, haystack: &'a str) -> StrSearchIter<'a, S::RevIter<'a>> {
+        StrSearchIter { haystack, offsets: self.searcher.rfind_overlapping_in(haystack.as_bytes()) }
+    }
+}
+
+
+/// An iterator over the byte offsets of a needle in a `str`. A non-empty needle can only match at
+/// char boundaries anyway, but the matches of an empty needle inside a char are skipped.
+pub struct StrSearchIter <'a, I> {
+    haystack: &'a str,
+    offsets: I,
+}
+
+impl <'a, I> Iterator for StrSearchIter<'a, I>
+    where I: Iterator<Item = usize>
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        let haystack = self.haystack;
+        self.offsets.find(|&position| haystack.is_char_boundary(position))
+    }
+}
+
+impl <'a, I> DoubleEndedIterator for StrSearchIter<'a, I>
+    where I: DoubleEndedIterator<Item = usize>
+{
+    fn next_back(&mut self) -> Option<usize> {
+        let haystack = self.haystack;
+        self.offsets.rfind(|&position| haystack.is_char_boundary(position))
+    }
+}
+
+
+/// An iterator over the parts of a `str` that match a needle.
+pub struct StrMatches <'a, I> {
+    haystack: &'a str,
+    len: usize,
+    offsets: I,
+}
+
+impl <'a, I> Iterator for StrMatches<'a, I>
+    where I: Iterator<Item = usize>
+{
+    type Item = &'a str;
+    fn next(&mut self) -> Option<&'a str> {
+        self.offsets.next().map(|position| &self.haystack[position .. position + self.len])
+    }
+}
+
+impl <'a, I> DoubleEndedIterator for StrMatches<'a, I>
+    where I: DoubleEndedIterator<Item = usize>
+{
+    fn next_back(&mut self) -> Option<&'a str> {
+        self.offsets.next_back().map(|position| &self.haystack[position .. position + self.len])
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::super::{BoyerMoore, Horspool, SearchIn, ReverseSearchIn, CountIn};
+
+    #[test]
+    pub fn test_find_in_str() {
+        let needle = BoyerMoore::new_str("ghi");
+        assert_eq!(Some(8), needle.find_first_in("abc def ghi jkl"));
+    }
+
+    #[test]
+    pub fn test_char_boundaries() {
+        let haystack = "ÿÿÿ ÿ ÿ";
+        let needle = Horspool::new_str("ÿ");
+        for position in needle.find_overlapping_in(haystack) {
+            assert!(haystack.is_char_boundary(position));
+        }
+        assert_eq!(5, needle.count_in(haystack));
+    }
+
+    #[test]
+    pub fn test_matches_in() {
+        let needle = Horspool::new_str("sea");
+        let haystack = "She sells sea shells on the sea shore.";
+        assert_eq!(vec!["sea", "sea"], needle.matches_in(haystack).collect::<Vec<&str>>());
+    }
+
+    #[test]
+    pub fn test_empty_needle() {
+        let needle = BoyerMoore::new_str("");
+        let haystack = "añb";
+        assert_eq!(vec![0, 1, 3, 4], needle.find_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(vec![4, 3, 1, 0], needle.rfind_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(vec!["", "", "", ""], needle.matches_in(haystack).collect::<Vec<&str>>());
+    }
+
+    #[test]
+    pub fn test_find_last_in_str() {
+        let needle = BoyerMoore::new_str("/");
+        assert_eq!(Some(8), needle.find_last_in("/usr/bin/ñandú"));
+    }
+}
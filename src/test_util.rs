@@ -0,0 +1,32 @@
+//! Helpers shared by the unit tests, for checking the searchers against a naive search over
+//! pseudo-random haystacks.
+
+/// A linear congruential generator. The tests only need a repeatable sequence over a small
+/// alphabet, to get lots of partial matches, so there is no need for a better one.
+pub struct Random {
+    seed: u32,
+}
+
+impl Random {
+    pub fn new(seed: u32) -> Random {
+        Random { seed }
+    }
+
+    /// The next number in the sequence, in the range `0 .. n`
+    pub fn below(&mut self, n: u32) -> u32 {
+        self.seed = self.seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (self.seed >> 16) % n
+    }
+}
+
+/// The start of every window of `haystack` of length `len` that is accepted by `matches`, which
+/// are the positions any search with overlapping matches should find.
+pub fn naive_find<T, F>(haystack: &[T], len: usize, mut matches: F) -> Vec<usize>
+    where F: FnMut(&[T]) -> bool
+{
+    haystack.windows(len)
+        .enumerate()
+        .filter(|&(_, window)| matches(window))
+        .map(|(i, _)| i)
+        .collect()
+}
@@ -0,0 +1,311 @@
+//! Implementation of the Two-Way search algorithm of Crochemore and Perrin. The needle is split at
+//! a critical factorization into a left and a right part. The right part is compared from left to
+//! right and the left part from right to left, and the shifts are chosen so that no character of
+//! the haystack is compared more than a constant number of times. This guarantees O(n + m) time
+//! in the worst case, using only constant extra space, even for the periodic inputs that make
+//! `BoyerMoore` and `Horspool` slow.
+//!
+//! Unlike the skip searches, Two-Way needs no bad character table, so any `Copy + Ord` type can
+//! be used as the alphabet.
+//!
+//! # Examples
+//!
+//! ```
+//! use needle::{TwoWay, SearchIn};
+//! let needle = TwoWay::new(b"aaab");
+//! let haystack = b"aaaaaaaaaaaaaaab";
+//! assert_eq!(Some(12), needle.find_first_in(haystack));
+//! ```
+use std::borrow::Cow;
+use std::cmp::max;
+use skip_search::position_after_match;
+use super::{SearchIn, MatchIn};
+
+pub struct TwoWay <'a, T: 'a + Clone> {
+    needle: Cow<'a, [T]>,
+    // the start of the right part of the critical factorization
+    crit_pos: usize,
+    // the period of the needle if it is periodic, otherwise a shift which is no larger than the period
+    period: usize,
+    // whether the needle is periodic, in which case the matched prefix is remembered across shifts
+    periodic: bool,
+}
+
+impl <'a, T> TwoWay<'a, T>
+    where T: Copy + Ord
+{
+    /// Construct a new Two-Way search object, and compute the critical factorization of the needle.
+    pub fn new(needle: &'a [T]) -> TwoWay<'a, T> {
+        TwoWay::from_cow(Cow::Borrowed(needle))
+    }
+
+    fn from_cow(needle: Cow<'a, [T]>) -> TwoWay<'a, T> {
+        let (left_less, period_less) = maximal_suffix(&needle, false);
+        let (left_greater, period_greater) = maximal_suffix(&needle, true);
+        let (crit_pos, period) = if left_less > left_greater {
+            (left_less, period_less)
+        } else {
+            (left_greater, period_greater)
+        };
+        let len = needle.len();
+        if period + crit_pos <= len && needle[.. crit_pos] == needle[period .. period + crit_pos] {
+            TwoWay { needle, crit_pos, period, periodic: true }
+        } else {
+            // the period is longer than either part, so this is a safe shift
+            let period = max(crit_pos, len - crit_pos) + 1;
+            TwoWay { needle, crit_pos, period, periodic: false }
+        }
+    }
+
+    /// The sequence that this object searches for.
+    pub fn needle(&self) -> &[T] {
+        &self.needle
+    }
+
+    /// Convert into a search object that owns its needle, copying the needle if it was borrowed.
+    pub fn into_owned(self) -> TwoWay<'static, T> {
+        TwoWay {
+            needle: Cow::Owned(self.needle.into_owned()),
+            ..self
+        }
+    }
+
+    // Find the needle at or after `position`. `memory` is the length of a prefix of the needle that
+    // is already known to match at `position`, and is updated as the search moves along.
+    fn find_from_position(&self, haystack: &[T], mut position: usize, memory: &mut usize) -> Option<usize> {
+        let needle = &self.needle[..];
+        let len = needle.len();
+        if len == 0 {
+            return if position <= haystack.len() { Some(position) } else { None };
+        }
+        'search: while position + len <= haystack.len() {
+            // match the right part, from left to right
+            let start = if self.periodic { max(self.crit_pos, *memory) } else { self.crit_pos };
+            for i in start .. len {
+                if needle[i] != haystack[position + i] {
+                    position += i - self.crit_pos + 1;
+                    *memory = 0;
+                    continue 'search;
+                }
+            }
+            // match the left part, from right to left
+            let start = if self.periodic { *memory } else { 0 };
+            for i in (start .. self.crit_pos).rev() {
+                if needle[i] != haystack[position + i] {
+                    position += self.period;
+                    if self.periodic {
+                        *memory = len - self.period;
+                    }
+                    continue 'search;
+                }
+            }
+            return Some(position);
+        }
+        None
+    }
+}
+
+impl <T> TwoWay<'static, T>
+    where T: Copy + Ord
+{
+    /// Construct a new Two-Way search object which owns its needle.
+    pub fn from_vec(needle: Vec<T>) -> TwoWay<'static, T> {
+        TwoWay::from_cow(Cow::Owned(needle))
+    }
+}
+
+// Computes the start and the period of the maximal suffix of `needle`, with respect to the usual
+// order of `T` or its reverse.
+fn maximal_suffix<T: Ord>(needle: &[T], reversed_order: bool) -> (usize, usize) {
+    let mut left = 0;
+    let mut right = 1;
+    let mut offset = 0;
+    let mut period = 1;
+    while let Some(a) = needle.get(right + offset) {
+        let b = &needle[left + offset];
+        if (a < b && !reversed_order) || (a > b && reversed_order) {
+            // the suffix is smaller, so the period is everything up to here
+            right += offset + 1;
+            offset = 0;
+            period = right - left;
+        } else if a == b {
+            if offset + 1 == period {
+                right += offset + 1;
+                offset = 0;
+            } else {
+                offset += 1;
+            }
+        } else {
+            // the suffix is larger, so start again from here
+            left = right;
+            right += 1;
+            offset = 0;
+            period = 1;
+        }
+    }
+    (left, period)
+}
+
+
+impl <'a, T> SearchIn<[T]> for TwoWay<'a, T>
+    where T: Copy + Ord
+{
+    type Iter<'b> = TwoWayIter<'b, T> where Self: 'b;
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will not find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{TwoWay, SearchIn};
+    /// let needle = TwoWay::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,6,12], needle.find_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_in<'b>(&'b self, haystack: &'b [T]) -> TwoWayIter<'b, T> {
+        TwoWayIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            memory: 0,
+            overlapping_matches: false,
+        }
+    }
+
+    /// Returns an iterator that will produce the indices of the needle in the haystack.
+    /// This iterator will find overlapping matches.
+    ///
+    /// # Example
+    /// ```
+    /// use needle::{TwoWay, SearchIn};
+    /// let needle = TwoWay::new(b"aaba");
+    /// let haystack = b"aabaabaabaabaaba";
+    /// assert_eq!(vec![0,3,6,9,12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    /// ```
+    fn find_overlapping_in<'b>(&'b self, haystack: &'b [T]) -> TwoWayIter<'b, T> {
+        TwoWayIter {
+            searcher: self,
+            haystack,
+            position: 0,
+            memory: 0,
+            overlapping_matches: true,
+        }
+    }
+}
+
+impl <'a, T> MatchIn<T> for TwoWay<'a, T>
+    where T: Copy + Ord
+{
+    fn match_len(&self) -> usize {
+        self.needle.len()
+    }
+}
+
+
+pub struct TwoWayIter <'a, T: 'a + Clone> {
+    searcher: &'a TwoWay<'a, T>,
+    haystack: &'a [T],
+    position: usize,
+    memory: usize,
+    overlapping_matches: bool,
+}
+
+impl <'a, T> Iterator for TwoWayIter<'a, T>
+    where T: Copy + Ord
+{
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        let searcher = self.searcher;
+        let len = searcher.needle.len();
+        searcher.find_from_position(self.haystack, self.position, &mut self.memory)
+            .inspect(|&position| {
+                if self.overlapping_matches && len > 0 {
+                    // no other match can start within the period, and a periodic needle's next
+                    // match would share all but a period's worth of the one just found
+                    self.position = position + searcher.period;
+                    self.memory = if searcher.periodic { len - searcher.period } else { 0 };
+                } else {
+                    self.position = position_after_match(position, len, self.overlapping_matches);
+                    self.memory = 0;
+                }
+            })
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::super::{SearchIn, CountIn};
+    use test_util::{Random, naive_find};
+
+    #[test]
+    pub fn test_simple() {
+        let needle = TwoWay::new(b"ghi");
+        let haystack = b"abc def ghi jkl";
+        assert_eq!(Some(8), needle.find_first_in(haystack));
+    }
+
+    #[test]
+    pub fn test_critical_factorization() {
+        let needle = TwoWay::new(b"abaabaa");
+        assert_eq!((2, 3, true), (needle.crit_pos, needle.period, needle.periodic));
+        let needle = TwoWay::new(b"banana");
+        assert!(!needle.periodic);
+    }
+
+    #[test]
+    pub fn test_periodic() {
+        let needle = TwoWay::new(b"abab");
+        let haystack = b"abababxabab";
+        assert_eq!(vec![0, 7], needle.find_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(vec![0, 2, 7], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(2, needle.count_in(haystack));
+    }
+
+    #[test]
+    pub fn test_worst_case_for_skip_search() {
+        let haystack = vec![b'a'; 100_000];
+        let mut needle = vec![b'a'; 1000];
+        needle[0] = b'b';
+        assert_eq!(None, TwoWay::new(&needle).find_first_in(&haystack));
+        let needle = vec![b'a'; 1000];
+        assert_eq!(100, TwoWay::new(&needle).count_in(&haystack));
+        assert_eq!(100_000 - 999, TwoWay::new(&needle).find_overlapping_in(&haystack).count());
+    }
+
+    #[test]
+    pub fn test_generic_alphabet() {
+        let haystack: Vec<char> = "the ĉapitalo of Ĉinio".chars().collect();
+        let needle: Vec<char> = "Ĉinio".chars().collect();
+        assert_eq!(Some(16), TwoWay::from_vec(needle).find_first_in(&haystack));
+    }
+
+    #[test]
+    pub fn test_agrees_with_naive() {
+        let mut random = Random::new(2024);
+        let haystack: Vec<u8> = (0 .. 3000).map(|_| b'a' + random.below(2) as u8).collect();
+        for _ in 0 .. 200 {
+            let len = 1 + random.below(8) as usize;
+            let needle: Vec<u8> = (0 .. len).map(|_| b'a' + random.below(2) as u8).collect();
+            let expected = naive_find(&haystack, len, |window| window == &needle[..]);
+            let searcher = TwoWay::new(&needle);
+            assert_eq!(expected, searcher.find_overlapping_in(&haystack).collect::<Vec<usize>>(), "{:?}", needle);
+            let mut non_overlapping = Vec::new();
+            for position in expected {
+                if non_overlapping.last().is_none_or(|&last| position >= last + len) {
+                    non_overlapping.push(position);
+                }
+            }
+            assert_eq!(non_overlapping, searcher.find_in(&haystack).collect::<Vec<usize>>(), "{:?}", needle);
+        }
+    }
+
+    #[test]
+    pub fn test_empty_needle() {
+        let needle = TwoWay::new(b"");
+        assert_eq!(vec![0, 1, 2, 3], needle.find_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(vec![0, 1, 2, 3], needle.find_overlapping_in(b"abc").collect::<Vec<usize>>());
+        assert_eq!(None, TwoWay::new(b"abcd").find_first_in(b"abc"));
+    }
+}
@@ -0,0 +1,79 @@
+// Empty needles match at every position, and haystacks shorter than the needle give no matches,
+// for every searcher and every kind of haystack.
+extern crate needle;
+
+use needle::{BoyerMoore, Horspool, AhoCorasick, SearchIn, ReverseSearchIn, SearchInIter, SearchInRead, CountIn};
+
+fn check_empty_needle<S>(needle: &S)
+    where S: ReverseSearchIn<[u8]> + SearchInIter<Vec<u8>> + for<'r> SearchInRead<&'r [u8]>
+{
+    let haystack = b"abc";
+    assert_eq!(vec![0, 1, 2, 3], needle.find_in(haystack).collect::<Vec<usize>>());
+    assert_eq!(vec![0, 1, 2, 3], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    assert_eq!(vec![3, 2, 1, 0], needle.rfind_in(haystack).collect::<Vec<usize>>());
+    assert_eq!(vec![3, 2, 1, 0], needle.rfind_overlapping_in(haystack).collect::<Vec<usize>>());
+    assert_eq!(vec![0, 1, 2, 3], needle.find_in_iter(haystack.to_vec()).collect::<Vec<usize>>());
+    let found: Vec<usize> = needle.find_in_read(&haystack[..]).map(Result::unwrap).collect();
+    assert_eq!(vec![0, 1, 2, 3], found);
+    assert_eq!(Some(0), needle.find_first_in(b""));
+    assert_eq!(Some(0), needle.find_last_in(b""));
+    assert_eq!(1, needle.count_in(b""));
+}
+
+fn check_short_haystack<S>(needle: &S)
+    where S: ReverseSearchIn<[u8]> + SearchInIter<Vec<u8>> + for<'r> SearchInRead<&'r [u8]>
+{
+    for haystack in &[&b""[..], b"a", b"ab"] {
+        assert_eq!(None, needle.find_first_in(haystack));
+        assert_eq!(None, needle.find_overlapping_in(haystack).next());
+        assert_eq!(None, needle.find_last_in(haystack));
+        assert_eq!(None, needle.find_in_iter(haystack.to_vec()).next());
+        assert!(needle.find_in_read(*haystack).next().is_none());
+    }
+}
+
+#[test]
+fn boyer_moore_empty_needle() {
+    check_empty_needle(&BoyerMoore::new(b""));
+}
+
+#[test]
+fn horspool_empty_needle() {
+    check_empty_needle(&Horspool::new(b""));
+}
+
+#[test]
+fn boyer_moore_short_haystack() {
+    check_short_haystack(&BoyerMoore::new(b"abc"));
+}
+
+#[test]
+fn horspool_short_haystack() {
+    check_short_haystack(&Horspool::new(b"abc"));
+}
+
+#[test]
+fn double_ended_empty_needle() {
+    let needle = BoyerMoore::new(b"");
+    let mut iter = needle.find_in(b"ab");
+    assert_eq!(Some(0), iter.next());
+    assert_eq!(Some(2), iter.next_back());
+    assert_eq!(Some(1), iter.next());
+    assert_eq!(None, iter.next_back());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn str_empty_needle() {
+    let needle = Horspool::new_str("");
+    assert_eq!(vec![0, 2, 3], needle.find_in("éa").collect::<Vec<usize>>());
+    assert_eq!(None, Horspool::new_str("abc").find_first_in("ab"));
+}
+
+#[test]
+fn aho_corasick_empty_and_long_patterns() {
+    let needle = AhoCorasick::new(&["", "abcd"]);
+    assert_eq!(vec![0, 1, 2, 3], needle.find_in(b"abc").collect::<Vec<usize>>());
+    let needle = AhoCorasick::new(&["abcd"]);
+    assert_eq!(None, needle.find_first_in(b"abc"));
+}
@@ -0,0 +1,98 @@
+// Uses the crate the way a downstream crate would, through its public API only.
+extern crate needle;
+
+use needle::{BoyerMoore, Horspool, SearchIn, CountIn, MatchIn, Match, SearchState};
+use needle::{BoyerMooreMemchr, HorspoolMemchr, MemchrSearch, NaiveSearch};
+
+#[test]
+fn boyer_moore_from_outside() {
+    let needle = BoyerMoore::new(b"Peter Piper");
+    let haystack = b"Peter Piper picked a peck of pickled peppers. A peck of pickled peppers Peter Piper picked.";
+    assert_eq!(vec![0, 72], needle.find_in(haystack).collect::<Vec<usize>>());
+    assert_eq!(Some(0), needle.find_first_in(haystack));
+    assert_eq!(2, needle.count_in(haystack));
+    assert!(needle.occurs_in(haystack));
+}
+
+#[test]
+fn horspool_from_outside() {
+    let needle = Horspool::new(b"aaba");
+    let haystack = b"aabaabaabaabaaba";
+    assert_eq!(vec![0, 3, 6, 9, 12], needle.find_overlapping_in(haystack).collect::<Vec<usize>>());
+    assert_eq!(3, needle.count_in(haystack));
+}
+
+// A searcher can be written against the traits, without naming the search algorithm
+fn count_separators<S: CountIn<[u8]>>(searcher: &S, buffer: &[u8]) -> usize {
+    searcher.count_in(buffer)
+}
+
+#[test]
+fn generic_over_searchers() {
+    let buffer = b"a\r\nb\r\nc";
+    assert_eq!(2, count_separators(&BoyerMoore::new(b"\r\n"), buffer));
+    assert_eq!(2, count_separators(&Horspool::new(b"\r\n"), buffer));
+    assert_eq!(2, count_separators(&BoyerMooreMemchr::new(b"\r\n"), buffer));
+    assert_eq!(2, count_separators(&HorspoolMemchr::new(b"\r\n"), buffer));
+    assert_eq!(2, count_separators(&MemchrSearch::new(b"\r\n"), buffer));
+    assert_eq!(2, count_separators(&NaiveSearch::new(b"\r\n"), buffer));
+}
+
+#[test]
+fn searcher_in_long_lived_struct() {
+    struct Parser {
+        separator: BoyerMoore<'static, u8>,
+    }
+    let parser = Parser { separator: BoyerMoore::from_vec(b", ".to_vec()) };
+    let line = String::from("one, two, three");
+    assert_eq!(vec![3, 8], parser.separator.find_in(line.as_bytes()).collect::<Vec<usize>>());
+}
+
+#[test]
+fn byte_searchers_agree_with_boyer_moore() {
+    let haystack = b"Peter Piper picked a peck of pickled peppers. A peck of pickled peppers Peter Piper picked.";
+    for needle in &[&b"Peter Piper"[..], b"pe", b"picked.", b"peppers Peter", b"P", b"Piper picked.!"] {
+        let expected: Vec<usize> = BoyerMoore::new(needle).find_overlapping_in(haystack).collect();
+        assert_eq!(expected, BoyerMooreMemchr::new(needle).find_overlapping_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(expected, HorspoolMemchr::new(needle).find_overlapping_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(expected, MemchrSearch::new(needle).find_overlapping_in(haystack).collect::<Vec<usize>>());
+        assert_eq!(expected, NaiveSearch::new(needle).find_overlapping_in(haystack).collect::<Vec<usize>>());
+    }
+}
+
+// Every exact searcher reports matches that span the needle
+fn matched_slices<'a, S: MatchIn<u8>>(searcher: &'a S, haystack: &'a [u8]) -> Vec<&'a [u8]> {
+    searcher.find_matches_in(haystack).map(|found| found.slice(haystack)).collect()
+}
+
+#[test]
+fn matches_from_outside() {
+    let haystack = b"Peter Piper picked a peck of pickled peppers.";
+    assert_eq!(vec![&b"pe"[..]; 4], matched_slices(&BoyerMoore::new(b"pe"), haystack));
+    assert_eq!(vec![&b"pick"[..], b"pick"], matched_slices(&Horspool::new(b"pick"), haystack));
+    assert_eq!(vec![&b"Piper"[..]], matched_slices(&HorspoolMemchr::new(b"Piper"), haystack));
+    let found: Vec<Match> = NaiveSearch::new(b"pp").find_overlapping_matches_in(haystack).collect();
+    assert_eq!(vec![Match::new(39, 41)], found);
+}
+
+// A long scan can stop after any match, keep only the state, and carry on later
+#[test]
+fn paused_scan_from_outside() {
+    let haystack = b"one, two, three, four, five";
+    let needle = Horspool::new(b", ");
+    let mut found = Vec::new();
+    let mut state: Option<SearchState> = None;
+    loop {
+        let mut matches = match state {
+            Some(state) => needle.resume_in(haystack, state),
+            None => needle.find_from(haystack, 0),
+        };
+        match matches.next() {
+            Some(position) => found.push(position),
+            None => break,
+        }
+        state = Some(matches.state());
+    }
+    assert_eq!(vec![3, 8, 15, 21], found);
+    assert_eq!(vec![8, 15], needle.find_in_range(haystack, 4 .. 17).collect::<Vec<usize>>());
+}